@@ -0,0 +1,111 @@
+// the CLI's `--target` switch: `Js` (the existing `compiler`/`codegen` path, producing JS run under
+// Deno) and `Native` (`llvm`, producing an ahead-of-time compiled binary with no JS runtime
+// dependency). Both backends start from the same type-checked `types::File` -- the split only
+// happens once `types::infer` is done -- so `main`'s `run`/`compile` commands can pick one through
+// this trait without caring how it actually lowers the IR.
+
+use crate::{compiler, llvm, sourcemap::SourceMap, types};
+use inkwell::context::Context;
+use std::{path::Path, str::FromStr};
+
+/// One compilation target: turns a type-checked `types::File` into its primary on-disk artifact.
+pub trait Backend {
+    /// Compiles `file` and writes the result to `out`. `source_path`/`source` are only needed to
+    /// label and embed the original text in the source map a `Js` target's `out` gets alongside
+    /// it; `Native` ignores both. Returns `false` (not an error) if `file` uses a construct
+    /// `Native` doesn't support yet -- the same "honest no" `llvm::compile_file` itself returns.
+    /// `Js` has no such construct to report `false` for (see `compiler::CompileError`), so any
+    /// failure there surfaces as an `Err` instead.
+    fn compile_to_file(
+        &self,
+        file: &types::File,
+        source_path: &Path,
+        source: &str,
+        out: &Path,
+    ) -> anyhow::Result<bool>;
+}
+
+/// Writes `js` to `out`, with a trailing `//# sourceMappingURL=` comment pointing at
+/// `<out>.map` (written alongside it, holding `source_map` as JSON) -- the usual way an emitted
+/// `.js` file stays associated with its source map, so a debugger or a Deno stack trace can find
+/// it.
+pub fn write_js_with_source_map(js: &str, source_map: &SourceMap, out: &Path) -> anyhow::Result<()> {
+    let map_file_name = format!("{}.map", out.file_name().unwrap().to_string_lossy());
+    std::fs::write(out.with_file_name(&map_file_name), serde_json::to_string(source_map)?)?;
+    std::fs::write(out, format!("{}\n//# sourceMappingURL={}\n", js, map_file_name))?;
+    Ok(())
+}
+
+pub struct Js;
+
+impl Backend for Js {
+    fn compile_to_file(
+        &self,
+        file: &types::File,
+        source_path: &Path,
+        source: &str,
+        out: &Path,
+    ) -> anyhow::Result<bool> {
+        let program = compiler::compile_file(file)?;
+        let (js, source_map) = crate::codegen::generate(&program, &source_path.display().to_string(), source);
+        write_js_with_source_map(&js, &source_map, out)?;
+        Ok(true)
+    }
+}
+
+pub struct Native;
+
+impl Backend for Native {
+    fn compile_to_file(
+        &self,
+        file: &types::File,
+        _source_path: &Path,
+        _source: &str,
+        out: &Path,
+    ) -> anyhow::Result<bool> {
+        let context = Context::create();
+        let module = match llvm::compile_file(&context, file) {
+            Some(module) => module,
+            None => return Ok(false),
+        };
+        llvm::emit_object(&module, out)?;
+        Ok(true)
+    }
+}
+
+/// Selects a `Backend` by name; used for the CLI's `--target js|native` flag.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Target {
+    Js,
+    Native,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "js" => Ok(Target::Js),
+            "native" => Ok(Target::Native),
+            other => Err(format!("unknown target `{}` (expected `js` or `native`)", other)),
+        }
+    }
+}
+
+impl Target {
+    /// The file extension this target's primary artifact conventionally gets, used to pick a
+    /// default `--out` path when the CLI caller didn't give one.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Target::Js => "js",
+            Target::Native => "o",
+        }
+    }
+
+    pub fn backend(&self) -> Box<dyn Backend> {
+        match self {
+            Target::Js => Box::new(Js),
+            Target::Native => Box::new(Native),
+        }
+    }
+}
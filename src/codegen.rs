@@ -0,0 +1,284 @@
+// emits JavaScript source text (plus a matching Source Map v3, via `sourcemap::MappingsBuilder`)
+// from an `estree::Program`. This only needs to cover the narrow slice of ESTree that
+// `compiler::compile_file` actually produces today (member/call/identifier/binary/logical/
+// assignment expressions, literals, `var` declarations, `if`/`while`/`return`, and function
+// declarations) -- it's not a general-purpose ESTree printer, and is expected to grow alongside
+// the compiler rather than ahead of it.
+
+use crate::{
+    estree,
+    sourcemap::{MappingsBuilder, SourceMap},
+};
+use either::Either;
+
+/// Tracks the generated-side line/column as text is appended, so mapping segments can be recorded
+/// without re-scanning the output built up so far.
+struct Writer {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+impl Writer {
+    fn new() -> Self {
+        Writer {
+            text: String::new(),
+            line: 0,
+            column: 0,
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        for ch in s.chars() {
+            self.push(ch);
+        }
+    }
+
+    fn push(&mut self, ch: char) {
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 0;
+        } else {
+            self.column += 1;
+        }
+        self.text.push(ch);
+    }
+}
+
+/// Records the writer's current position as the generated-side counterpart of `loc`'s start.
+fn mark(writer: &Writer, loc: &Option<estree::SourceLocation>, mappings: &mut MappingsBuilder) {
+    if let Some(loc) = loc {
+        mappings.add(writer.line, writer.column, loc.start.line - 1, loc.start.column);
+    }
+}
+
+pub fn generate(program: &estree::Program, source_path: &str, source: &str) -> (String, SourceMap) {
+    let mut writer = Writer::new();
+    let mut mappings = MappingsBuilder::new();
+
+    for stmt in &program.body {
+        match stmt {
+            Either::Left(directive) => {
+                mark(&writer, &directive.loc, &mut mappings);
+                writer.push_str(&format!("{:?};\n", directive.directive));
+            }
+            Either::Right(stmt) => generate_statement(stmt, &mut writer, &mut mappings),
+        }
+    }
+
+    (
+        writer.text,
+        mappings.finish(String::from(source_path), String::from(source)),
+    )
+}
+
+fn generate_statement(stmt: &estree::Statement, writer: &mut Writer, mappings: &mut MappingsBuilder) {
+    match stmt {
+        estree::Statement::Expression { expression, loc } => {
+            mark(writer, loc, mappings);
+            generate_expression(expression, writer);
+            writer.push_str(";\n");
+        }
+        estree::Statement::Block { body, loc } => {
+            mark(writer, loc, mappings);
+            writer.push_str("{\n");
+            for stmt in body {
+                generate_statement(stmt, writer, mappings);
+            }
+            writer.push_str("}\n");
+        }
+        estree::Statement::If {
+            test,
+            consequent,
+            alternate,
+            loc,
+        } => {
+            mark(writer, loc, mappings);
+            writer.push_str("if (");
+            generate_expression(test, writer);
+            writer.push_str(") ");
+            generate_statement(consequent, writer, mappings);
+            if let Some(alternate) = alternate {
+                writer.push_str("else ");
+                generate_statement(alternate, writer, mappings);
+            }
+        }
+        estree::Statement::While { test, body, loc } => {
+            mark(writer, loc, mappings);
+            writer.push_str("while (");
+            generate_expression(test, writer);
+            writer.push_str(") ");
+            generate_statement(body, writer, mappings);
+        }
+        estree::Statement::Return { argument, loc } => {
+            mark(writer, loc, mappings);
+            writer.push_str("return");
+            if let Some(argument) = argument {
+                writer.push(' ');
+                generate_expression(argument, writer);
+            }
+            writer.push_str(";\n");
+        }
+        estree::Statement::VariableDeclaration { declarations, loc, .. } => {
+            mark(writer, loc, mappings);
+            writer.push_str("var ");
+            for (i, declarator) in declarations.iter().enumerate() {
+                if i > 0 {
+                    writer.push_str(", ");
+                }
+                generate_pattern(&declarator.id, writer);
+                if let Some(init) = &declarator.init {
+                    writer.push_str(" = ");
+                    generate_expression(init, writer);
+                }
+            }
+            writer.push_str(";\n");
+        }
+        estree::Statement::FunctionDeclaration { id, params, body, loc } => {
+            mark(writer, loc, mappings);
+            writer.push_str("function ");
+            writer.push_str(&id.name);
+            writer.push('(');
+            for (i, param) in params.iter().enumerate() {
+                if i > 0 {
+                    writer.push_str(", ");
+                }
+                generate_pattern(param, writer);
+            }
+            writer.push_str(") {\n");
+            for stmt in &body.body {
+                match stmt {
+                    Either::Left(directive) => writer.push_str(&format!("{:?};\n", directive.directive)),
+                    Either::Right(stmt) => generate_statement(stmt, writer, mappings),
+                }
+            }
+            writer.push_str("}\n");
+        }
+        _ => unimplemented!("codegen for {:?} is not supported yet", stmt),
+    }
+}
+
+fn generate_pattern(pattern: &estree::Pattern, writer: &mut Writer) {
+    match pattern {
+        estree::Pattern::Identifier { name, .. } => writer.push_str(name),
+        _ => unimplemented!("codegen for {:?} is not supported yet", pattern),
+    }
+}
+
+fn binary_operator(op: &estree::BinaryOperator) -> &'static str {
+    match op {
+        estree::BinaryOperator::DoubleEqual => "==",
+        estree::BinaryOperator::NotDoubleEqual => "!=",
+        estree::BinaryOperator::TripleEqual => "===",
+        estree::BinaryOperator::NotTripleEqual => "!==",
+        estree::BinaryOperator::Less => "<",
+        estree::BinaryOperator::LessEqual => "<=",
+        estree::BinaryOperator::Greater => ">",
+        estree::BinaryOperator::GreaterEqual => ">=",
+        estree::BinaryOperator::LeftShift => "<<",
+        estree::BinaryOperator::RightShift => ">>",
+        estree::BinaryOperator::UnsignedRightShift => ">>>",
+        estree::BinaryOperator::Add => "+",
+        estree::BinaryOperator::Subtract => "-",
+        estree::BinaryOperator::Multiply => "*",
+        estree::BinaryOperator::Divide => "/",
+        estree::BinaryOperator::Modulus => "%",
+        estree::BinaryOperator::BitwiseOr => "|",
+        estree::BinaryOperator::BitwiseXor => "^",
+        estree::BinaryOperator::BitwiseAnd => "&",
+        estree::BinaryOperator::In => "in",
+        estree::BinaryOperator::Instanceof => "instanceof",
+    }
+}
+
+fn logical_operator(op: &estree::LogicalOperator) -> &'static str {
+    match op {
+        estree::LogicalOperator::Or => "||",
+        estree::LogicalOperator::And => "&&",
+    }
+}
+
+fn assignment_operator(op: &estree::AssignmentOperator) -> &'static str {
+    match op {
+        estree::AssignmentOperator::Equal => "=",
+        estree::AssignmentOperator::AddEqual => "+=",
+        estree::AssignmentOperator::SubtractEqual => "-=",
+        estree::AssignmentOperator::MultiplyEqual => "*=",
+        estree::AssignmentOperator::DivideEqual => "/=",
+        estree::AssignmentOperator::ModulusEqual => "%=",
+        estree::AssignmentOperator::LeftShiftEqual => "<<=",
+        estree::AssignmentOperator::RightShiftEqual => ">>=",
+        estree::AssignmentOperator::UnsignedRightShiftEqual => ">>>=",
+        estree::AssignmentOperator::BitwiseOrEqual => "|=",
+        estree::AssignmentOperator::BitwiseXorEqual => "^=",
+        estree::AssignmentOperator::BitwiseAndEqual => "&=",
+    }
+}
+
+fn generate_expression(expr: &estree::Expression, writer: &mut Writer) {
+    match expr {
+        estree::Expression::Identifier { name, .. } => writer.push_str(name),
+        estree::Expression::Literal {
+            value: estree::Value::String(value),
+            ..
+        } => writer.push_str(&format!("{:?}", value)),
+        estree::Expression::Literal {
+            value: estree::Value::Number(value),
+            ..
+        } => writer.push_str(&value.to_string()),
+        estree::Expression::Literal {
+            value: estree::Value::Boolean(value),
+            ..
+        } => writer.push_str(if *value { "true" } else { "false" }),
+        estree::Expression::Member {
+            object,
+            property,
+            computed,
+            ..
+        } => {
+            generate_expression(object, writer);
+            if *computed {
+                writer.push('[');
+                generate_expression(property, writer);
+                writer.push(']');
+            } else {
+                writer.push('.');
+                generate_expression(property, writer);
+            }
+        }
+        estree::Expression::Call { callee, arguments, .. } => {
+            generate_expression(callee, writer);
+            writer.push('(');
+            for (i, argument) in arguments.iter().enumerate() {
+                if i > 0 {
+                    writer.push_str(", ");
+                }
+                generate_expression(argument, writer);
+            }
+            writer.push(')');
+        }
+        estree::Expression::Binary { operator, left, right, .. } => {
+            writer.push('(');
+            generate_expression(left, writer);
+            writer.push_str(&format!(" {} ", binary_operator(operator)));
+            generate_expression(right, writer);
+            writer.push(')');
+        }
+        estree::Expression::Logical { operator, left, right, .. } => {
+            writer.push('(');
+            generate_expression(left, writer);
+            writer.push_str(&format!(" {} ", logical_operator(operator)));
+            generate_expression(right, writer);
+            writer.push(')');
+        }
+        estree::Expression::Assignment { operator, left, right, .. } => {
+            match left {
+                Either::Left(pattern) => generate_pattern(pattern, writer),
+                Either::Right(expr) => generate_expression(expr, writer),
+            }
+            writer.push_str(&format!(" {} ", assignment_operator(operator)));
+            generate_expression(right, writer);
+        }
+        _ => unimplemented!("codegen for {:?} is not supported yet", expr),
+    }
+}
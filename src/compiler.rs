@@ -1,64 +1,253 @@
-use crate::{estree, syntax};
+use crate::{estree, syntax, types};
 use either::Either;
 
-fn compile_identifier(id: &syntax::Identifier) -> Option<estree::Expression> {
-    match id.name.as_str() {
-        "print" => Some(estree::Expression::Member {
+/// Every `types::Expression`/`types::Statement` variant lowers to `estree` today -- unlike
+/// `llvm.rs`, this backend has no type it can't represent -- but a lowering failure still needs to
+/// abort the whole compile rather than quietly producing a program with statements missing, so
+/// `compile_expression`/`compile_statement` report one of these instead of just returning `None`.
+#[derive(Debug, thiserror::Error)]
+#[error("the js target doesn't support {construct} yet, at {span:?}")]
+pub struct CompileError {
+    construct: &'static str,
+    span: syntax::Span,
+}
+
+fn loc(span: syntax::Span) -> estree::SourceLocation {
+    estree::SourceLocation {
+        source: None,
+        start: estree::Position {
+            line: span.start.row + 1,
+            column: span.start.column,
+        },
+        end: estree::Position {
+            line: span.end.row + 1,
+            column: span.end.column,
+        },
+    }
+}
+
+// this can't fail -- `semantics::check`/`types::infer` already guaranteed `name` resolves, and
+// anything that isn't one of the special forms below still compiles straight through to a
+// same-named JS identifier -- so unlike `compile_expression`/`compile_statement` it isn't fallible
+fn compile_identifier(name: &str, span: syntax::Span) -> estree::Expression {
+    match name {
+        "print" => estree::Expression::Member {
             object: Box::new(estree::Expression::Identifier {
                 name: String::from("console"),
+                loc: None,
             }),
             property: Box::new(estree::Expression::Identifier {
                 name: String::from("log"),
+                loc: None,
             }),
             computed: false,
-        }),
-        "args" => Some(estree::Expression::Member {
+            loc: Some(loc(span)),
+        },
+        "args" => estree::Expression::Member {
             object: Box::new(estree::Expression::Identifier {
                 name: String::from("Deno"),
+                loc: None,
             }),
             property: Box::new(estree::Expression::Identifier {
                 name: String::from("args"),
+                loc: None,
             }),
             computed: false,
-        }),
-        _ => None,
+            loc: Some(loc(span)),
+        },
+        // resolves to the `test` global the test-runner bootstrap script defines (see
+        // `test_runner::run_file`), not to anything built into Deno itself
+        "test" => estree::Expression::Identifier {
+            name: String::from("test"),
+            loc: Some(loc(span)),
+        },
+        // anything else is a `let`/`function`/parameter name -- `semantics::check` and
+        // `types::infer` already guaranteed it resolves, so it compiles straight through to a
+        // same-named JS identifier rather than one of the special forms above
+        _ => estree::Expression::Identifier {
+            name: String::from(name),
+            loc: Some(loc(span)),
+        },
     }
 }
 
-fn compile_expression(expr: &syntax::Expression) -> Option<estree::Expression> {
+fn binary_operator(op: syntax::BinaryOp) -> estree::BinaryOperator {
+    match op {
+        syntax::BinaryOp::Add => estree::BinaryOperator::Add,
+        syntax::BinaryOp::Subtract => estree::BinaryOperator::Subtract,
+        syntax::BinaryOp::Multiply => estree::BinaryOperator::Multiply,
+        syntax::BinaryOp::Divide => estree::BinaryOperator::Divide,
+        syntax::BinaryOp::Modulus => estree::BinaryOperator::Modulus,
+        // `==`/`!=` would work identically here since both sides are already unified to `Number`,
+        // but we emit the strict form anyway so the generated JS never relies on coercion
+        syntax::BinaryOp::Equal => estree::BinaryOperator::TripleEqual,
+        syntax::BinaryOp::NotEqual => estree::BinaryOperator::NotTripleEqual,
+        syntax::BinaryOp::Less => estree::BinaryOperator::Less,
+        syntax::BinaryOp::LessEqual => estree::BinaryOperator::LessEqual,
+        syntax::BinaryOp::Greater => estree::BinaryOperator::Greater,
+        syntax::BinaryOp::GreaterEqual => estree::BinaryOperator::GreaterEqual,
+    }
+}
+
+fn logical_operator(op: syntax::LogicalOp) -> estree::LogicalOperator {
+    match op {
+        syntax::LogicalOp::And => estree::LogicalOperator::And,
+        syntax::LogicalOp::Or => estree::LogicalOperator::Or,
+    }
+}
+
+fn assignment_operator(op: syntax::AssignOp) -> estree::AssignmentOperator {
+    match op {
+        syntax::AssignOp::Equal => estree::AssignmentOperator::Equal,
+        syntax::AssignOp::AddEqual => estree::AssignmentOperator::AddEqual,
+        syntax::AssignOp::SubtractEqual => estree::AssignmentOperator::SubtractEqual,
+        syntax::AssignOp::MultiplyEqual => estree::AssignmentOperator::MultiplyEqual,
+        syntax::AssignOp::DivideEqual => estree::AssignmentOperator::DivideEqual,
+    }
+}
+
+fn compile_expression(expr: &types::Expression) -> Result<estree::Expression, CompileError> {
     match expr {
-        syntax::Expression::Call(syntax::Call {
+        types::Expression::Call {
             function,
+            function_span,
             arguments,
-        }) => Some(estree::Expression::Call {
-            callee: Box::new(compile_identifier(function)?),
-            arguments: arguments.iter().filter_map(compile_expression).collect(),
+            span,
+            ..
+        } => Ok(estree::Expression::Call {
+            callee: Box::new(compile_identifier(function, *function_span)),
+            arguments: arguments
+                .iter()
+                .map(compile_expression)
+                .collect::<Result<Vec<_>, _>>()?,
+            loc: Some(loc(*span)),
+        }),
+        types::Expression::Id { name, span, .. } => Ok(compile_identifier(name, *span)),
+        types::Expression::Lit { value, span, .. } => Ok(estree::Expression::Literal {
+            value: match value {
+                types::Literal::Str(value) => estree::Value::String(value.clone()),
+                types::Literal::Num(value) => estree::Value::Number(*value),
+                types::Literal::Bool(value) => estree::Value::Boolean(*value),
+            },
+            loc: Some(loc(*span)),
         }),
-        syntax::Expression::Id(id) => compile_identifier(id),
-        syntax::Expression::Lit(syntax::Literal::Str(value)) => Some(estree::Expression::Literal {
-            value: estree::Value::String(value.clone()),
+        types::Expression::Binary { op, left, right, span, .. } => Ok(estree::Expression::Binary {
+            operator: binary_operator(*op),
+            left: Box::new(compile_expression(left)?),
+            right: Box::new(compile_expression(right)?),
+            loc: Some(loc(*span)),
+        }),
+        types::Expression::Logical { op, left, right, span, .. } => Ok(estree::Expression::Logical {
+            operator: logical_operator(*op),
+            left: Box::new(compile_expression(left)?),
+            right: Box::new(compile_expression(right)?),
+            loc: Some(loc(*span)),
+        }),
+        types::Expression::Assign { name, op, value, span, .. } => Ok(estree::Expression::Assignment {
+            operator: assignment_operator(*op),
+            left: Either::Left(estree::Pattern::Identifier {
+                name: name.clone(),
+                loc: None,
+            }),
+            right: Box::new(compile_expression(value)?),
+            loc: Some(loc(*span)),
         }),
     }
 }
 
-fn compile_statement(stmt: &syntax::Statement) -> Option<estree::Statement> {
+/// Compiles each statement in a `{ ... }` body -- an `if`/`while`/function body is just a nested
+/// block -- bailing out on the first one that fails instead of silently leaving it out, same as
+/// `compile_file` does for the top-level body.
+fn compile_block(body: &[types::Statement]) -> Result<Vec<estree::Statement>, CompileError> {
+    body.iter().map(compile_statement).collect()
+}
+
+fn compile_statement(stmt: &types::Statement) -> Result<estree::Statement, CompileError> {
     match stmt {
-        syntax::Statement::Expr(expr) => {
+        types::Statement::Expr(expr) => {
+            let span = expr.span();
             let compiled = compile_expression(expr)?;
-            Some(estree::Statement::Expression {
+            Ok(estree::Statement::Expression {
                 expression: Box::new(compiled),
+                loc: Some(loc(span)),
             })
         }
+        types::Statement::Let { name, init, span, .. } => Ok(estree::Statement::VariableDeclaration {
+            declarations: vec![estree::VariableDeclarator {
+                id: estree::Pattern::Identifier {
+                    name: name.clone(),
+                    loc: None,
+                },
+                init: match init {
+                    Some(init) => Some(Box::new(compile_expression(init)?)),
+                    None => None,
+                },
+                loc: Some(loc(*span)),
+            }],
+            kind: estree::DeclarationKind::Var,
+            loc: Some(loc(*span)),
+        }),
+        types::Statement::If {
+            test,
+            consequent,
+            alternate,
+            span,
+        } => Ok(estree::Statement::If {
+            test: Box::new(compile_expression(test)?),
+            consequent: Box::new(estree::Statement::Block {
+                body: compile_block(consequent)?,
+                loc: None,
+            }),
+            alternate: match alternate {
+                Some(alternate) => Some(Box::new(estree::Statement::Block {
+                    body: compile_block(alternate)?,
+                    loc: None,
+                })),
+                None => None,
+            },
+            loc: Some(loc(*span)),
+        }),
+        types::Statement::While { test, body, span } => Ok(estree::Statement::While {
+            test: Box::new(compile_expression(test)?),
+            body: Box::new(estree::Statement::Block {
+                body: compile_block(body)?,
+                loc: None,
+            }),
+            loc: Some(loc(*span)),
+        }),
+        types::Statement::Return { value, span } => Ok(estree::Statement::Return {
+            argument: match value {
+                Some(value) => Some(Box::new(compile_expression(value)?)),
+                None => None,
+            },
+            loc: Some(loc(*span)),
+        }),
+        types::Statement::FunctionDecl {
+            name, params, body, span, ..
+        } => Ok(estree::Statement::FunctionDeclaration {
+            id: estree::Identifier {
+                name: name.clone(),
+                loc: None,
+            },
+            params: params
+                .iter()
+                .map(|(param, _)| estree::Pattern::Identifier {
+                    name: param.clone(),
+                    loc: None,
+                })
+                .collect(),
+            body: estree::FunctionBody {
+                body: compile_block(body)?.into_iter().map(Either::Right).collect(),
+                loc: None,
+            },
+            loc: Some(loc(*span)),
+        }),
     }
 }
 
-pub fn compile_file(file: &syntax::File) -> Option<estree::Program> {
-    Some(estree::Program {
-        body: file
-            .body
-            .iter()
-            .filter_map(compile_statement)
-            .map(Either::Right)
-            .collect(),
+pub fn compile_file(file: &types::File) -> Result<estree::Program, CompileError> {
+    Ok(estree::Program {
+        body: compile_block(&file.body)?.into_iter().map(Either::Right).collect(),
+        loc: None,
     })
 }
@@ -1,14 +1,28 @@
-use crate::{parser, text};
+use crate::{
+    fmt, parser, semantics,
+    syntax::{self, Node as _},
+    text, types,
+};
 use lspower::lsp::{
     Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
     DidOpenTextDocumentParams, MessageType, Position, Range, SemanticToken, SemanticTokenType,
     TextDocumentContentChangeEvent,
 };
-use std::{fmt::Debug, ptr, rc::Rc, thread};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Debug,
+    panic::AssertUnwindSafe,
+    ptr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use tokio::sync::{mpsc, oneshot};
-use tree_sitter::{Node, Point, Tree};
+use tree_sitter::{InputEdit, Node, Point, Tree};
 use url::Url;
 
 #[derive(Debug)]
@@ -46,41 +60,86 @@ pub fn token_types() -> Vec<SemanticTokenType> {
 
 #[salsa::query_group(Storage)]
 trait QueryGroup: salsa::Database {
-    // we don't track versions because we only allow full text sync
     #[salsa::input]
     fn opened_files(&self) -> im::HashSet<Url>;
 
+    // files known only because they were found on disk while indexing the workspace (as opposed
+    // to `opened_files`, which the editor has told us about directly); a file that's in both sets
+    // is open in the editor, whose buffer is authoritative over whatever's on disk
+    #[salsa::input]
+    fn workspace_files(&self) -> im::HashSet<Url>;
+
+    #[salsa::input]
+    fn source_text(&self, key: Url) -> Arc<String>;
+
+    // the tree produced for the current `source_text`, either by a fresh parse (on open, or
+    // after a full-text change) or by an incremental reparse seeded from the previous tree (on an
+    // edit with a range); kept as an input rather than derived from `source_text` because
+    // tree-sitter's incremental reparse needs the *previous* parser::tree, not just the new text
     #[salsa::input]
-    fn source_text(&self, key: Url) -> Rc<String>;
+    fn parsed_tree(&self, key: Url) -> Option<Arc<Ast>>;
+
+    fn known(&self, key: Url) -> bool;
 
     fn source_index(&self, key: Url) -> Option<text::Index>;
 
-    fn ast(&self, key: Url) -> Option<Rc<Ast>>;
+    fn ast(&self, key: Url) -> Option<Arc<Ast>>;
+
+    fn file(&self, key: Url) -> Option<Arc<syntax::File>>;
 
     fn diagnostics(&self, key: Url) -> im::Vector<Diagnostic>;
 
     fn semantic_tokens(&self, key: Url) -> im::Vector<SemanticToken>;
+
+    fn formatted(&self, key: Url) -> Option<Arc<String>>;
+}
+
+fn known(db: &dyn QueryGroup, key: Url) -> bool {
+    db.opened_files().contains(&key) || db.workspace_files().contains(&key)
 }
 
 fn source_index(db: &dyn QueryGroup, key: Url) -> Option<text::Index> {
-    if db.opened_files().contains(&key) {
+    if db.known(key.clone()) {
         Some(text::Index::new(&db.source_text(key)))
     } else {
         None
     }
 }
 
-fn ast(db: &dyn QueryGroup, key: Url) -> Option<Rc<Ast>> {
-    if db.opened_files().contains(&key) {
-        let mut parser = parser::parser();
-        let text: &str = &db.source_text(key);
-        let tree = parser.parse(text, None).unwrap();
-        Some(Rc::new(Ast(tree)))
+fn ast(db: &dyn QueryGroup, key: Url) -> Option<Arc<Ast>> {
+    if db.known(key.clone()) {
+        db.parsed_tree(key)
     } else {
         None
     }
 }
 
+// the parsed `syntax::File` that `semantics::check` and `types::infer` walk (the latter producing
+// the typed IR `compiler::compile_file` lowers), built from whatever `ast` currently holds; a file with syntax errors still produces a
+// best-effort `File` (`Node::make` just drops the parts it can't make sense of), so semantic
+// analysis runs on whatever did parse
+fn file(db: &dyn QueryGroup, key: Url) -> Option<Arc<syntax::File>> {
+    let text = db.source_text(key.clone());
+    let tree = db.ast(key)?;
+    syntax::File::make(&text, &tree.0.root_node()).map(Arc::new)
+}
+
+// computes the `tree_sitter::Point` reached after appending `inserted` starting at `start`;
+// tree-sitter points count rows by '\n' and columns by byte offset within the row, matching the
+// columns tree-sitter itself hands back from `Node::start_position`/`end_position`
+fn point_after(start: Point, inserted: &str) -> Point {
+    match inserted.rfind('\n') {
+        None => Point {
+            row: start.row,
+            column: start.column + inserted.len(),
+        },
+        Some(last_newline) => Point {
+            row: start.row + inserted.matches('\n').count(),
+            column: inserted.len() - last_newline - 1,
+        },
+    }
+}
+
 #[salsa::database(Storage)]
 struct Database {
     storage: salsa::Storage<Self>,
@@ -92,12 +151,25 @@ impl Default for Database {
             storage: salsa::Storage::default(),
         };
         db.set_opened_files(im::HashSet::new());
+        db.set_workspace_files(im::HashSet::new());
         db
     }
 }
 
 impl salsa::Database for Database {}
 
+// lets us hand out read-only `salsa::Snapshot<Database>`s to worker threads while the owning
+// thread keeps the writable `Database` for the input-setting methods; salsa bumps its internal
+// revision counter on every `set_*` call, which is what lets outstanding snapshot queries notice
+// they're stale and unwind with `salsa::Cancelled` instead of racing the write
+impl salsa::ParallelDatabase for Database {
+    fn snapshot(&self) -> salsa::Snapshot<Self> {
+        salsa::Snapshot::new(Database {
+            storage: self.storage.snapshot(),
+        })
+    }
+}
+
 trait Processable<T> {
     fn process(self, db: &mut Database) -> T;
 }
@@ -119,10 +191,157 @@ where
     }
 }
 
+// a read-only counterpart to `Processable`: implementors only ever see a `Snapshot`, so they're
+// safe to run concurrently with the owning thread (and with each other) on a worker thread
+trait SnapshotProcessable<T> {
+    fn process(self, db: &salsa::Snapshot<Database>) -> T;
+}
+
+type RequestId = u64;
+
+// tracks which cancellable read requests are outstanding for a given URI, so that in principle a
+// write could be made to proactively abandon the threads computing them instead of just letting
+// salsa's revision bump cancel the query the next time it touches an input; today we only use it
+// to hand out fresh ids, but it's the hook a future `didChange` handler would use to do that
+#[derive(Default)]
+struct PendingRequests {
+    next_id: AtomicU64,
+    by_uri: Mutex<HashMap<Url, HashSet<RequestId>>>,
+}
+
+impl PendingRequests {
+    fn register(&self, uri: Url) -> RequestId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.by_uri.lock().unwrap().entry(uri).or_default().insert(id);
+        id
+    }
+
+    fn complete(&self, uri: &Url, id: RequestId) {
+        if let Some(ids) = self.by_uri.lock().unwrap().get_mut(uri) {
+            ids.remove(&id);
+        }
+    }
+}
+
+// dispatched like a `Request`, but `handle` only has to snapshot the database and hand the
+// worker pool the job that actually runs `process`, so the owning thread is immediately free to
+// handle the next request (in particular, a `set_source_text` that should cancel this one)
+struct CancellableEnvelope<T, U> {
+    params: T,
+    uri: Url,
+    tx: oneshot::Sender<Result<U, salsa::Cancelled>>,
+    pending: Arc<PendingRequests>,
+    pool: threadpool::ThreadPool,
+}
+
+impl<T, U> Request for Option<CancellableEnvelope<T, U>>
+where
+    T: SnapshotProcessable<U> + Send + 'static,
+    U: Send + 'static,
+{
+    fn handle(&mut self, db: &mut Database) {
+        if let Some(CancellableEnvelope {
+            params,
+            uri,
+            tx,
+            pending,
+            pool,
+        }) = self.take()
+        {
+            let snapshot = db.snapshot();
+            let id = pending.register(uri.clone());
+            pool.execute(move || {
+                let result =
+                    salsa::Cancelled::catch(AssertUnwindSafe(|| params.process(&snapshot)));
+                pending.complete(&uri, id);
+                let _ = tx.send(result);
+            });
+        }
+    }
+}
+
+// a `$/progress` notification for a batch operation fanned out across the worker pool; `State`
+// forwards these over a `tokio::sync::mpsc` channel so the async LSP connection can relay them to
+// the client via `window/workDoneProgress/create` + `$/progress`, even though the work itself runs
+// on the non-async owning/worker threads
+#[derive(Debug)]
+pub enum Progress {
+    Begin { title: String },
+    Report { done: usize, total: usize },
+    End,
+}
+
+// fans a single query out across every currently known file (opened in the editor or indexed from
+// disk), using one worker-pool job per URI,
+// so e.g. "diagnose the whole workspace" takes roughly as long as its slowest file rather than the
+// sum of all of them
+struct WorkspaceEnvelope<U> {
+    title: String,
+    query: fn(&salsa::Snapshot<Database>, Url) -> U,
+    tx: oneshot::Sender<im::HashMap<Url, U>>,
+    pool: threadpool::ThreadPool,
+    progress: Option<mpsc::Sender<Progress>>,
+}
+
+impl<U> Request for Option<WorkspaceEnvelope<U>>
+where
+    U: Clone + Send + 'static,
+{
+    fn handle(&mut self, db: &mut Database) {
+        if let Some(WorkspaceEnvelope {
+            title,
+            query,
+            tx,
+            pool,
+            progress,
+        }) = self.take()
+        {
+            // union opened and disk-indexed files, same as `known` -- a workspace-wide operation
+            // like `diagnose_workspace` needs to cover files the editor never opened too
+            let files: Vec<Url> = db.opened_files().union(db.workspace_files()).into_iter().collect();
+            let total = files.len();
+            if let Some(progress) = &progress {
+                let _ = progress.blocking_send(Progress::Begin { title });
+            }
+            let (result_tx, result_rx) = std::sync::mpsc::channel();
+            for uri in files {
+                let snapshot = db.snapshot();
+                let result_tx = result_tx.clone();
+                pool.execute(move || {
+                    let result = query(&snapshot, uri.clone());
+                    let _ = result_tx.send((uri, result));
+                });
+            }
+            drop(result_tx);
+            // the pool jobs above are all independent of the owning thread from here on, so we can
+            // collect their results (and report progress as each one lands) on a throwaway thread
+            // instead of blocking the request loop
+            thread::spawn(move || {
+                let mut map = im::HashMap::new();
+                for (done, (uri, result)) in result_rx.iter().take(total).enumerate() {
+                    map.insert(uri, result);
+                    if let Some(progress) = &progress {
+                        let _ = progress.blocking_send(Progress::Report {
+                            done: done + 1,
+                            total,
+                        });
+                    }
+                }
+                if let Some(progress) = &progress {
+                    let _ = progress.blocking_send(Progress::End);
+                }
+                let _ = tx.send(map);
+            });
+        }
+    }
+}
+
 type BoxedRequest = Box<dyn Request + Send>;
 
 pub struct State {
     tx: mpsc::Sender<BoxedRequest>,
+    pending: Arc<PendingRequests>,
+    pool: threadpool::ThreadPool,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -131,6 +350,8 @@ pub enum AsyncError {
     Send,
     #[error("failed to receive result from state loop")]
     Recv,
+    #[error("cancelled by a newer edit")]
+    Cancelled,
 }
 
 impl From<mpsc::error::SendError<BoxedRequest>> for AsyncError {
@@ -151,14 +372,22 @@ pub trait LspMessage {
 
 impl LspMessage for AsyncError {
     fn message_type(&self) -> MessageType {
-        MessageType::Error
+        match self {
+            // the client will simply ask again once the document settles, so this isn't worth
+            // surfacing as a warning or an error
+            AsyncError::Cancelled => MessageType::Info,
+            AsyncError::Send | AsyncError::Recv => MessageType::Error,
+        }
     }
 }
 
 impl State {
     pub fn new() -> Self {
         let (tx, mut rx) = mpsc::channel::<BoxedRequest>(1);
-        // we do this in a non-async thread because our db isn't thread-safe
+        // the owning thread still does all of the writing (and any non-cancellable processing)
+        // itself, but cancellable and per-file read requests are handed off to the worker pool, so
+        // a slow `diagnostics` computation no longer blocks a `didChange` behind it, and opening a
+        // project with many files can use every core instead of just one
         thread::spawn(move || {
             let mut db = Database::default();
             // https://stackoverflow.com/a/52521592
@@ -166,7 +395,11 @@ impl State {
                 request.handle(&mut db);
             }
         });
-        State { tx }
+        State {
+            tx,
+            pending: Arc::new(PendingRequests::default()),
+            pool: threadpool::Builder::new().build(),
+        }
     }
 
     // confusing given that the Processable trait has a different method with the same name
@@ -180,6 +413,52 @@ impl State {
         let result = rx.await?;
         Ok(result)
     }
+
+    // counterpart to `process` for read-only queries: the result can come back as `Cancelled` if
+    // a write superseded it before the worker thread finished
+    async fn process_cancellable<T, U>(&self, params: T, uri: Url) -> Result<U, AsyncError>
+    where
+        T: SnapshotProcessable<U> + Send + 'static,
+        U: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(Box::new(Some(CancellableEnvelope {
+                params,
+                uri,
+                tx,
+                pending: Arc::clone(&self.pending),
+                pool: self.pool.clone(),
+            })))
+            .await?;
+        rx.await?
+            .map_err(|_: salsa::Cancelled| AsyncError::Cancelled)
+    }
+
+    // runs `query` against every opened or disk-indexed file concurrently and collects the per-file results; used
+    // to publish diagnostics (or recompute semantic tokens) for a whole workspace without
+    // serializing file after file through the single owning thread
+    async fn process_workspace<U>(
+        &self,
+        title: &str,
+        query: fn(&salsa::Snapshot<Database>, Url) -> U,
+        progress: Option<mpsc::Sender<Progress>>,
+    ) -> Result<im::HashMap<Url, U>, AsyncError>
+    where
+        U: Clone + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(Box::new(Some(WorkspaceEnvelope {
+                title: String::from(title),
+                query,
+                tx,
+                pool: self.pool.clone(),
+                progress,
+            })))
+            .await?;
+        Ok(rx.await?)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -228,7 +507,10 @@ impl Database {
         // we always call set_source_text, even if the file is already opened, because we want to
         // give the client the benefit of the doubt and assume that we've made a bookkeeping
         // mistake, rather than risk possibly dropping data
-        self.set_source_text(uri.clone(), Rc::new(text));
+        let mut parser = parser::parser();
+        let tree = parser.parse(&text, None).unwrap();
+        self.set_source_text(uri.clone(), Arc::new(text));
+        self.set_parsed_tree(uri.clone(), Some(Arc::new(Ast(tree))));
         let mut files = self.opened_files();
         if let Some(_) = files.insert(uri.clone()) {
             Err(AlreadyOpenError { uri })
@@ -255,12 +537,89 @@ impl State {
     }
 }
 
+// the LSP wire format gives positions as (line, UTF-16 code unit), while tree-sitter wants byte
+// offsets, both absolute (for `InputEdit::*_byte`) and row-relative (for `InputEdit::*_position`);
+// this walks `source` once to translate a `Position` into both
+fn locate(source: &str, position: Position) -> (usize, Point) {
+    let mut line_start = 0;
+    let mut lines = source.split('\n');
+    for _ in 0..position.line {
+        line_start += lines.next().map_or(0, str::len) + 1;
+    }
+    let line = lines.next().unwrap_or("");
+    let mut units = 0;
+    let mut column = 0;
+    for ch in line.chars() {
+        if units >= position.character {
+            break;
+        }
+        units += ch.len_utf16() as u32;
+        column += ch.len_utf8();
+    }
+    (
+        line_start + column,
+        Point {
+            row: position.line as usize,
+            column,
+        },
+    )
+}
+
 impl Database {
-    fn edit_document(&mut self, uri: Url, text: String) -> Result<(), NotYetOpenedError> {
+    fn edit_document(
+        &mut self,
+        uri: Url,
+        changes: Vec<TextDocumentContentChangeEvent>,
+    ) -> Result<(), NotYetOpenedError> {
+        // a URI that was never opened or indexed has no `source_text`/`parsed_tree` input set at
+        // all (salsa panics on reading one), so its edits start from an empty document instead
+        let known = self.known(uri.clone());
+        let mut text = if known {
+            String::from(self.source_text(uri.clone()).as_str())
+        } else {
+            String::new()
+        };
+        let mut tree = if known {
+            self.parsed_tree(uri.clone()).map(|ast| ast.0.clone())
+        } else {
+            None
+        };
+        for change in changes {
+            match change.range {
+                // a range-less change still means "replace the whole document", so we drop the
+                // old tree and let it be reparsed from scratch below
+                None => {
+                    text = change.text;
+                    tree = None;
+                }
+                Some(Range { start, end }) => {
+                    let (start_byte, start_position) = locate(&text, start);
+                    let (old_end_byte, old_end_position) = locate(&text, end);
+                    let new_end_byte = start_byte + change.text.len();
+                    let new_end_position = point_after(start_position, &change.text);
+                    text.replace_range(start_byte..old_end_byte, &change.text);
+                    if let Some(tree) = tree.as_mut() {
+                        tree.edit(&InputEdit {
+                            start_byte,
+                            old_end_byte,
+                            new_end_byte,
+                            start_position,
+                            old_end_position,
+                            new_end_position,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut parser = parser::parser();
+        let new_tree = parser.parse(&text, tree.as_ref());
+
         // we always call set_source_text, even if the file hadn't yet been opened, because we want
         // to give the client the benefit of the doubt and assume that we've made a bookkeeping
         // mistake, rather than risk possibly dropping data
-        self.set_source_text(uri.clone(), Rc::new(text));
+        self.set_source_text(uri.clone(), Arc::new(text));
+        self.set_parsed_tree(uri.clone(), new_tree.map(|tree| Arc::new(Ast(tree))));
         let mut files = self.opened_files();
         if !files.contains(&uri) {
             files.insert(uri.clone());
@@ -272,45 +631,10 @@ impl Database {
     }
 }
 
-#[derive(Debug, Eq, thiserror::Error, PartialEq)]
-pub enum EditError {
-    #[error(transparent)]
-    NotYetOpened(#[from] NotYetOpenedError),
-    #[error("incremental sync (when full text was expected) for version {version} of {uri}")]
-    IncrementalSync {
-        // same fields as lsp_types::VersionedTextDocumentIdentifier
-        uri: Url,
-        version: i32,
-    },
-}
-
-impl LspMessage for EditError {
-    fn message_type(&self) -> MessageType {
-        match self {
-            EditError::NotYetOpened(error) => error.message_type(),
-            EditError::IncrementalSync { .. } => MessageType::Error,
-        }
-    }
-}
-
-impl Processable<Result<(), EditError>> for DidChangeTextDocumentParams {
-    fn process(self, db: &mut Database) -> Result<(), EditError> {
+impl Processable<Result<(), NotYetOpenedError>> for DidChangeTextDocumentParams {
+    fn process(self, db: &mut Database) -> Result<(), NotYetOpenedError> {
         let doc = self.text_document;
-        let mut changes = self.content_changes;
-        if let Some(TextDocumentContentChangeEvent {
-            range: None,
-            range_length: None,
-            text,
-        }) = changes.pop()
-        {
-            if changes.is_empty() {
-                return Ok(db.edit_document(doc.uri, text)?);
-            }
-        }
-        Err(EditError::IncrementalSync {
-            uri: doc.uri,
-            version: doc.version,
-        })
+        db.edit_document(doc.uri, self.content_changes)
     }
 }
 
@@ -318,19 +642,24 @@ impl State {
     pub async fn edit_document(
         &self,
         params: DidChangeTextDocumentParams,
-    ) -> Result<(), OpError<EditError>> {
+    ) -> Result<(), OpError<NotYetOpenedError>> {
         self.process(params).await?.map_err(OpError::Op)
     }
 }
 
 impl Database {
     fn close_document(&mut self, uri: Url) -> Result<(), NotYetOpenedError> {
-        // Salsa doesn't seem to support removing inputs https://github.com/salsa-rs/salsa/issues/37
-        // so we just free most of the memory (hopefully?) by setting it to the empty string; also,
-        // we always call set_source_text, even if the file hadn't yet been opened, because we want
-        // to give the client the benefit of the doubt and assume that we've just made a bookkeeping
-        // mistake
-        self.set_source_text(uri.clone(), Rc::new(String::from("")));
+        // if the file is still known from the on-disk workspace index, leave its (possibly stale)
+        // text and tree in place rather than wiping them, since the editor buffer was only ever
+        // shadowing the disk copy, not the only copy
+        if !self.workspace_files().contains(&uri) {
+            // Salsa doesn't seem to support removing inputs https://github.com/salsa-rs/salsa/issues/37
+            // so we just free most of the memory (hopefully?) by setting it to the empty string; also,
+            // we always call set_source_text, even if the file hadn't yet been opened, because we want
+            // to give the client the benefit of the doubt and assume that we've just made a bookkeeping
+            // mistake
+            self.set_source_text(uri.clone(), Arc::new(String::from("")));
+        }
         let mut files = self.opened_files();
         if let None = files.remove(&uri) {
             Err(NotYetOpenedError { uri })
@@ -356,6 +685,68 @@ impl State {
     }
 }
 
+impl Database {
+    // registers disk-authoritative text for `uri`, found by walking the workspace or reported by
+    // a file watcher; an editor-opened buffer always wins, so this is a no-op while the file is
+    // also in `opened_files`
+    fn index_workspace_file(&mut self, uri: Url, text: String) {
+        if self.opened_files().contains(&uri) {
+            return;
+        }
+        let mut parser = parser::parser();
+        let tree = parser.parse(&text, None).unwrap();
+        self.set_source_text(uri.clone(), Arc::new(text));
+        self.set_parsed_tree(uri.clone(), Some(Arc::new(Ast(tree))));
+        let mut files = self.workspace_files();
+        files.insert(uri);
+        self.set_workspace_files(files);
+    }
+
+    fn remove_workspace_file(&mut self, uri: Url) {
+        let mut files = self.workspace_files();
+        if files.remove(&uri).is_some() {
+            self.set_workspace_files(files);
+        }
+    }
+}
+
+struct IndexWorkspaceFiles(Vec<(Url, String)>);
+
+impl Processable<()> for IndexWorkspaceFiles {
+    fn process(self, db: &mut Database) {
+        for (uri, text) in self.0 {
+            db.index_workspace_file(uri, text);
+        }
+    }
+}
+
+struct RemoveWorkspaceFile(Url);
+
+impl Processable<()> for RemoveWorkspaceFile {
+    fn process(self, db: &mut Database) {
+        db.remove_workspace_file(self.0);
+    }
+}
+
+impl State {
+    // walks the workspace root for `.qn` files and registers each one, so `ast`/`diagnostics`/
+    // `semantic_tokens` work for files the editor hasn't opened via `textDocument/didOpen`
+    pub async fn index_workspace(&self, files: Vec<(Url, String)>) -> Result<(), AsyncError> {
+        self.process(IndexWorkspaceFiles(files)).await
+    }
+
+    // called from a file-watcher (native `notify`, or the `workspace/didChangeWatchedFiles`
+    // fallback) when a `.qn` file on disk is created or modified outside the editor
+    pub async fn update_workspace_file(&self, uri: Url, text: String) -> Result<(), AsyncError> {
+        self.process(IndexWorkspaceFiles(vec![(uri, text)])).await
+    }
+
+    // called from the same file-watcher when a `.qn` file on disk is deleted
+    pub async fn remove_workspace_file(&self, uri: Url) -> Result<(), AsyncError> {
+        self.process(RemoveWorkspaceFile(uri)).await
+    }
+}
+
 fn make_diagnostic(range: Range, message: String, severity: DiagnosticSeverity) -> Diagnostic {
     let mut diag = Diagnostic::new_simple(range, message);
     diag.severity = Some(severity);
@@ -390,17 +781,98 @@ fn diagnostics_helper(node: &Node, index: &text::Index) -> im::Vector<Diagnostic
     }
 }
 
+fn pos(p: Position) -> (u32, u32) {
+    (p.line, p.character)
+}
+
+fn range_contains(outer: &Range, inner: &Range) -> bool {
+    pos(outer.start) <= pos(inner.start) && pos(inner.end) <= pos(outer.end)
+}
+
+// tree-sitter's error recovery frequently produces a cascade of sibling ERROR/MISSING nodes for a
+// single underlying typo; keep only the most informative diagnostic at each position by dropping
+// one whose range is entirely contained within another's, following the prefix-replacement
+// strategy rustc's borrow checker uses for buffered move errors. A MISSING diagnostic at its own
+// distinct position is never dropped, since it usually pinpoints the exact fix even when a
+// surrounding ERROR also covers that span.
+fn dedupe_diagnostics(candidates: im::Vector<Diagnostic>) -> im::Vector<Diagnostic> {
+    let mut buffered: BTreeMap<(u32, u32), Diagnostic> = BTreeMap::new();
+    for diagnostic in candidates {
+        let key = pos(diagnostic.range.start);
+        let is_missing = diagnostic.message.ends_with("missing");
+
+        let contained_by_other = buffered
+            .iter()
+            .any(|(&other_key, other)| other_key != key && range_contains(&other.range, &diagnostic.range));
+        if contained_by_other && !is_missing {
+            continue;
+        }
+
+        buffered.retain(|&other_key, other| {
+            other_key == key
+                || !range_contains(&diagnostic.range, &other.range)
+                || other.message.ends_with("missing")
+        });
+
+        buffered
+            .entry(key)
+            .and_modify(|existing| {
+                if pos(diagnostic.range.end) > pos(existing.range.end) {
+                    *existing = diagnostic.clone();
+                }
+            })
+            .or_insert(diagnostic);
+    }
+    buffered.into_iter().map(|(_, diagnostic)| diagnostic).collect()
+}
+
 fn diagnostics(db: &dyn QueryGroup, key: Url) -> im::Vector<Diagnostic> {
-    match (db.source_index(key.clone()), db.ast(key)) {
-        (Some(index), Some(tree)) => diagnostics_helper(&tree.0.root_node(), &index),
+    match (db.source_index(key.clone()), db.ast(key.clone())) {
+        (Some(index), Some(tree)) => {
+            let mut diagnostics =
+                dedupe_diagnostics(diagnostics_helper(&tree.0.root_node(), &index));
+            // semantic analysis assumes a clean parse; a file with syntax errors may have produced
+            // a `File` with whole subtrees silently dropped by `Node::make`'s filter_map, so
+            // running it here would risk reporting confusing, partial-parse-induced errors on top
+            // of the syntax errors already shown
+            if diagnostics.is_empty() {
+                if let Some(file) = db.file(key) {
+                    let semantic_errors = semantics::check(&file);
+                    for error in &semantic_errors {
+                        let span = error.span();
+                        diagnostics.push_back(make_diagnostic(
+                            Range::new(index.to_lsp(span.start), index.to_lsp(span.end)),
+                            error.to_string(),
+                            DiagnosticSeverity::Error,
+                        ));
+                    }
+
+                    // type inference assumes every name in the file already resolved, so it's only
+                    // worth running once semantic analysis comes back clean too
+                    if semantic_errors.is_empty() {
+                        if let Err(type_errors) = types::infer(&file) {
+                            for error in &type_errors {
+                                let span = error.span();
+                                diagnostics.push_back(make_diagnostic(
+                                    Range::new(index.to_lsp(span.start), index.to_lsp(span.end)),
+                                    error.to_string(),
+                                    DiagnosticSeverity::Error,
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+            diagnostics
+        }
         _ => im::vector![],
     }
 }
 
 struct DiagnosticsRequest(Url);
 
-impl Processable<im::Vector<Diagnostic>> for DiagnosticsRequest {
-    fn process(self, db: &mut Database) -> im::Vector<Diagnostic> {
+impl SnapshotProcessable<im::Vector<Diagnostic>> for DiagnosticsRequest {
+    fn process(self, db: &salsa::Snapshot<Database>) -> im::Vector<Diagnostic> {
         let DiagnosticsRequest(uri) = self;
         db.diagnostics(uri)
     }
@@ -408,7 +880,19 @@ impl Processable<im::Vector<Diagnostic>> for DiagnosticsRequest {
 
 impl State {
     pub async fn get_diagnostics(&self, uri: Url) -> Result<im::Vector<Diagnostic>, AsyncError> {
-        self.process(DiagnosticsRequest(uri)).await
+        self.process_cancellable(DiagnosticsRequest(uri.clone()), uri)
+            .await
+    }
+
+    // "publish diagnostics for the whole workspace", fanned out across the worker pool instead of
+    // awaiting `get_diagnostics` for one file at a time; pass `progress` to receive a Begin/Report
+    // (one per completed file)/End sequence the caller can relay as `$/progress` notifications
+    pub async fn diagnose_workspace(
+        &self,
+        progress: Option<mpsc::Sender<Progress>>,
+    ) -> Result<im::HashMap<Url, im::Vector<Diagnostic>>, AsyncError> {
+        self.process_workspace("diagnostics", |db, uri| db.diagnostics(uri), progress)
+            .await
     }
 }
 
@@ -526,8 +1010,8 @@ fn semantic_tokens(db: &dyn QueryGroup, key: Url) -> im::Vector<SemanticToken> {
 
 struct TokensRequest(Url);
 
-impl Processable<im::Vector<SemanticToken>> for TokensRequest {
-    fn process(self, db: &mut Database) -> im::Vector<SemanticToken> {
+impl SnapshotProcessable<im::Vector<SemanticToken>> for TokensRequest {
+    fn process(self, db: &salsa::Snapshot<Database>) -> im::Vector<SemanticToken> {
         let TokensRequest(uri) = self;
         db.semantic_tokens(uri)
     }
@@ -538,7 +1022,30 @@ impl State {
         &self,
         uri: Url,
     ) -> Result<im::Vector<SemanticToken>, AsyncError> {
-        self.process(TokensRequest(uri)).await
+        self.process_cancellable(TokensRequest(uri.clone()), uri)
+            .await
+    }
+}
+
+fn formatted(db: &dyn QueryGroup, key: Url) -> Option<Arc<String>> {
+    let text = db.source_text(key.clone());
+    let tree = db.ast(key)?;
+    Some(Arc::new(fmt::format(&tree.0.root_node(), &text)))
+}
+
+struct FormatRequest(Url);
+
+impl SnapshotProcessable<Option<Arc<String>>> for FormatRequest {
+    fn process(self, db: &salsa::Snapshot<Database>) -> Option<Arc<String>> {
+        let FormatRequest(uri) = self;
+        db.formatted(uri)
+    }
+}
+
+impl State {
+    pub async fn get_formatted(&self, uri: Url) -> Result<Option<Arc<String>>, AsyncError> {
+        self.process_cancellable(FormatRequest(uri.clone()), uri)
+            .await
     }
 }
 
@@ -573,7 +1080,14 @@ mod tests {
         let mut db = Database::default();
         let uri = Url::parse("file:///tmp/foo.qn").unwrap();
         assert_eq!(
-            db.edit_document(uri.clone(), String::from("bar")),
+            db.edit_document(
+                uri.clone(),
+                vec![TextDocumentContentChangeEvent {
+                    range: None,
+                    range_length: None,
+                    text: String::from("bar"),
+                }],
+            ),
             Err(NotYetOpenedError { uri: uri.clone() }),
         );
         assert_eq!(db.opened_files(), im::hashset![uri.clone()]);
@@ -606,27 +1120,54 @@ mod tests {
 
     #[test]
     fn test_incremental_sync() {
-        let (mut db, uri) = foo_db(String::from("foo"));
+        let (mut db, uri) = foo_db(String::from("print(\"a\")"));
         let params = DidChangeTextDocumentParams {
             text_document: VersionedTextDocumentIdentifier {
                 uri: uri.clone(),
                 version: 2,
             },
             content_changes: vec![TextDocumentContentChangeEvent {
-                range: Some(make_range(0, 0, 0, 3)),
+                range: Some(make_range(0, 7, 0, 8)),
                 range_length: None,
-                text: String::from("bar"),
+                text: String::from("b"),
             }],
         };
+        params.process(&mut db).unwrap();
+        let new_contents: &str = &db.source_text(uri.clone());
+        assert_eq!(new_contents, "print(\"b\")");
+        let ast = db.ast(uri).unwrap();
         assert_eq!(
-            params.process(&mut db),
-            Err(EditError::IncrementalSync {
-                uri: uri.clone(),
-                version: 2
-            }),
+            ast.0.root_node().to_sexp(),
+            "(source_file (call function: (identifier) arguments: (arguments (string))))",
         );
+    }
+
+    #[test]
+    fn test_incremental_sync_multiple_edits_in_order() {
+        // two edits in the same notification must be applied in order, each against the result of
+        // the last, rather than both against the original text
+        let (mut db, uri) = foo_db(String::from("foo"));
+        let params = DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: uri.clone(),
+                version: 2,
+            },
+            content_changes: vec![
+                TextDocumentContentChangeEvent {
+                    range: Some(make_range(0, 0, 0, 0)),
+                    range_length: None,
+                    text: String::from("X"),
+                },
+                TextDocumentContentChangeEvent {
+                    range: Some(make_range(0, 1, 0, 1)),
+                    range_length: None,
+                    text: String::from("Y"),
+                },
+            ],
+        };
+        params.process(&mut db).unwrap();
         let new_contents: &str = &db.source_text(uri);
-        assert_eq!(new_contents, "foo");
+        assert_eq!(new_contents, "XYfoo");
     }
 
     #[test]
@@ -684,6 +1225,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_dedupe_diagnostics_drops_contained_range() {
+        let outer = make_error(0, 0, 0, 10, "syntax error");
+        let inner = make_error(0, 2, 0, 4, "syntax error");
+        assert_eq!(
+            dedupe_diagnostics(im::vector![outer.clone(), inner]),
+            im::vector![outer],
+        );
+    }
+
+    #[test]
+    fn test_dedupe_diagnostics_keeps_missing_at_distinct_position() {
+        let outer = make_error(0, 0, 0, 10, "syntax error");
+        let missing = make_error(0, 10, 0, 10, "syntax missing");
+        assert_eq!(
+            dedupe_diagnostics(im::vector![outer.clone(), missing.clone()]),
+            im::vector![outer, missing],
+        );
+    }
+
+    #[test]
+    fn test_dedupe_diagnostics_keeps_disjoint_ranges() {
+        let first = make_error(0, 6, 0, 14, "syntax error");
+        let second = make_error(0, 24, 0, 24, "syntax missing");
+        assert_eq!(
+            dedupe_diagnostics(im::vector![first.clone(), second.clone()]),
+            im::vector![first, second],
+        );
+    }
+
     #[test]
     fn test_tokens_hello_world() {
         let (db, uri) = foo_db({
@@ -1,15 +1,36 @@
 use either::Either;
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 
 // https://github.com/estree/estree/blob/0fa6c005fa452f1f970b3923d5faa38178906d08/es5.md
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+/// A 1-based line, 0-based column position, matching the ESTree `Position` spec (and, not
+/// coincidentally, `tree_sitter::Point` with its row renumbered from 0-based to 1-based).
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// See the ESTree `SourceLocation` spec. Every node below carries one of these, optionally, under
+/// its `loc` key; absent until `compiler::compile_file` threads it in from the `syntax` AST's own
+/// `Span`s, which is why golden JSON recorded before this existed is still valid.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SourceLocation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub struct Identifier {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loc: Option<SourceLocation>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum Value {
     String(String),
@@ -36,101 +57,160 @@ impl PartialEq for Value {
 
 impl Eq for Value {}
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub enum Literal {
     Literal {
         value: Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "RegExpLiteral")]
     RegExp {
         regex: RegExp,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct RegExp {
     pub pattern: String,
     pub flags: String,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub struct Program {
-    #[serde(serialize_with = "serialize_vec_either_untagged")]
+    #[serde(with = "vec_either_untagged")]
     pub body: Vec<Either<Directive, Statement>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loc: Option<SourceLocation>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+impl Program {
+    /// Parses ESTree JSON produced by some other tool (acorn, espree, ...) back into this crate's
+    /// own types, so e.g. a future `quench fmt` on plain JS could reuse `codegen` without quench
+    /// having parsed the file itself.
+    pub fn from_json(json: &str) -> serde_json::Result<Program> {
+        serde_json::from_str(json)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub enum Statement {
     #[serde(rename = "ExpressionStatement")]
-    Expression { expression: Box<Expression> },
+    Expression {
+        expression: Box<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
+    },
 
     #[serde(rename = "BlockStatement")]
-    Block { body: Vec<Statement> },
+    Block {
+        body: Vec<Statement>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
+    },
 
     #[serde(rename = "EmptyStatement")]
-    Empty,
+    Empty {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
+    },
 
     #[serde(rename = "DebuggerStatement")]
-    Debugger,
+    Debugger {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
+    },
 
     #[serde(rename = "WIthStatement")]
     WIth {
         object: Box<Expression>,
         body: Box<Statement>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "ReturnStatement")]
-    Return { argument: Option<Box<Expression>> },
+    Return {
+        argument: Option<Box<Expression>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
+    },
 
     #[serde(rename = "LabeledStatement")]
     Labeled {
         label: Identifier,
         body: Box<Statement>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "BreakStatement")]
-    Break { label: Option<Identifier> },
+    Break {
+        label: Option<Identifier>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
+    },
 
     #[serde(rename = "ContinueStatement")]
-    Continue { label: Option<Identifier> },
+    Continue {
+        label: Option<Identifier>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
+    },
 
     #[serde(rename = "IfStatement")]
     If {
         test: Box<Expression>,
         consequent: Box<Statement>,
         alternate: Option<Box<Statement>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "SwitchStatement")]
     Switch {
         discriminant: Box<Expression>,
         cases: Vec<SwitchCase>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "ThrowStatement")]
-    Throw { argument: Box<Expression> },
+    Throw {
+        argument: Box<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
+    },
 
     #[serde(rename = "TryStatement")]
     Try {
         block: BlockStatement,
         handler: Option<CatchClause>,
         finalizer: Option<BlockStatement>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "WhileStatement")]
     While {
         test: Box<Expression>,
         body: Box<Statement>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "DoWhileStatement")]
     DoWhile {
         body: Box<Statement>,
         test: Box<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "ForStatement")]
@@ -140,6 +220,8 @@ pub enum Statement {
         test: Option<Box<Expression>>,
         update: Option<Box<Expression>>,
         body: Box<Statement>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "ForInStatement")]
@@ -148,100 +230,133 @@ pub enum Statement {
         left: Either<VariableDeclaration, Pattern>,
         right: Box<Expression>,
         body: Box<Statement>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     FunctionDeclaration {
         id: Identifier,
         params: Vec<Pattern>,
         body: FunctionBody,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     VariableDeclaration {
         declarations: Vec<VariableDeclarator>,
         kind: DeclarationKind,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(rename = "ExpressionStatement", tag = "type")]
 pub struct Directive {
     pub expression: Literal,
     pub directive: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loc: Option<SourceLocation>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub struct BlockStatement {
     pub body: Vec<Statement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loc: Option<SourceLocation>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(rename = "BlockStatement", tag = "type")]
 pub struct FunctionBody {
-    #[serde(serialize_with = "serialize_vec_either_untagged")]
+    #[serde(with = "vec_either_untagged")]
     pub body: Vec<Either<Directive, Statement>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loc: Option<SourceLocation>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub struct SwitchCase {
     pub test: Option<Box<Expression>>,
     pub consequent: Vec<Statement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loc: Option<SourceLocation>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub struct CatchClause {
     pub param: Pattern,
     pub body: BlockStatement,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loc: Option<SourceLocation>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub struct VariableDeclaration {
     pub declarations: Vec<VariableDeclarator>,
     pub kind: DeclarationKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loc: Option<SourceLocation>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum DeclarationKind {
     #[serde(rename = "var")]
     Var,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub struct VariableDeclarator {
     pub id: Pattern,
     pub init: Option<Box<Expression>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loc: Option<SourceLocation>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub enum Expression {
     Identifier {
         name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     Literal {
         value: Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     RegExpLiteral {
         regex: RegExp,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "ThisExpression")]
-    This,
+    This {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
+    },
 
     #[serde(rename = "ArrayExpression")]
     Array {
         elements: Vec<Option<Expression>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "ObjectExpression")]
     Object {
         properties: Vec<Property>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "FunctionExpression")]
@@ -249,6 +364,8 @@ pub enum Expression {
         id: Option<Identifier>,
         params: Vec<Pattern>,
         body: FunctionBody,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "UnaryExpression")]
@@ -256,6 +373,8 @@ pub enum Expression {
         operator: UnaryOperator,
         prefix: bool,
         argument: Box<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "UpdateExpression")]
@@ -263,6 +382,8 @@ pub enum Expression {
         operator: UpdateOperator,
         argument: Box<Expression>,
         prefix: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "BinaryExpression")]
@@ -270,6 +391,8 @@ pub enum Expression {
         operator: BinaryOperator,
         left: Box<Expression>,
         right: Box<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "AssignmentExpression")]
@@ -278,6 +401,8 @@ pub enum Expression {
         #[serde(with = "either::serde_untagged")]
         left: Either<Pattern, Box<Expression>>,
         right: Box<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "LogicalExpression")]
@@ -285,6 +410,8 @@ pub enum Expression {
         operator: LogicalOperator,
         left: Box<Expression>,
         right: Box<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "MemberExpression")]
@@ -292,6 +419,8 @@ pub enum Expression {
         object: Box<Expression>,
         property: Box<Expression>,
         computed: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "ConditionalExpression")]
@@ -299,36 +428,46 @@ pub enum Expression {
         test: Box<Expression>,
         alternate: Box<Expression>,
         consequent: Box<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "CallExpression")]
     Call {
         callee: Box<Expression>,
         arguments: Vec<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "NewExpression")]
     New {
         callee: Box<Expression>,
         arguments: Vec<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     #[serde(rename = "SequenceExpression")]
     Sequence {
         expressions: Vec<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub struct Property {
     #[serde(with = "either::serde_untagged")]
     pub key: Either<Literal, Identifier>,
     pub value: Box<Expression>,
     pub kind: PropertyKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loc: Option<SourceLocation>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum PropertyKind {
     #[serde(rename = "init")]
     Init,
@@ -340,7 +479,7 @@ pub enum PropertyKind {
     Set,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum UnaryOperator {
     #[serde(rename = "-")]
     Negative,
@@ -364,7 +503,7 @@ pub enum UnaryOperator {
     Delete,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum UpdateOperator {
     #[serde(rename = "++")]
     Increment,
@@ -373,7 +512,7 @@ pub enum UpdateOperator {
     Decrement,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum BinaryOperator {
     #[serde(rename = "==")]
     DoubleEqual,
@@ -439,7 +578,7 @@ pub enum BinaryOperator {
     Instanceof,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum AssignmentOperator {
     #[serde(rename = "=")]
     Equal,
@@ -478,7 +617,7 @@ pub enum AssignmentOperator {
     BitwiseAndEqual,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum LogicalOperator {
     #[serde(rename = "||")]
     Or,
@@ -487,46 +626,75 @@ pub enum LogicalOperator {
     And,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub enum Pattern {
     Identifier {
         name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 
     MemberExpression {
         object: Box<Expression>,
         property: Box<Expression>,
         computed: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        loc: Option<SourceLocation>,
     },
 }
 
 // https://github.com/bluss/either/blob/1.6.1/src/serde_untagged_optional.rs
+//
+// `Program.body`/`FunctionBody.body` can't reuse `either::serde_untagged` itself: on the way out, an
+// untagged enum just needs `Serialize` on both sides, but on the way back in, `Directive` and
+// `Statement::Expression` both serialize under the identical `"ExpressionStatement"` type tag, so
+// there's no single discriminant to dispatch on. We disambiguate structurally instead -- `Directive`
+// requires a `directive` field `Statement` doesn't have, so trying it first and falling back to
+// `Statement` on failure recovers the right side every time.
+mod vec_either_untagged {
+    use either::Either;
+    use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize)]
+    #[serde(untagged)]
+    enum UntaggedEither<L, R> {
+        Left(L),
+        Right(R),
+    }
 
-#[derive(Serialize)]
-#[serde(untagged)]
-enum UntaggedEither<L, R> {
-    Left(L),
-    Right(R),
-}
+    pub fn serialize<L, R, S>(this: &Vec<Either<L, R>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        L: Serialize,
+        R: Serialize,
+    {
+        let untagged: Vec<_> = this
+            .iter()
+            .map(|either| match either {
+                Either::Left(ref left) => UntaggedEither::Left(left),
+                Either::Right(ref right) => UntaggedEither::Right(right),
+            })
+            .collect();
+        untagged.serialize(serializer)
+    }
 
-fn serialize_vec_either_untagged<L, R, S>(
-    this: &Vec<Either<L, R>>,
-    serializer: S,
-) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-    L: Serialize,
-    R: Serialize,
-{
-    let untagged: Vec<_> = this
-        .iter()
-        .map(|either| match either {
-            Either::Left(ref left) => UntaggedEither::Left(left),
-            Either::Right(ref right) => UntaggedEither::Right(right),
-        })
-        .collect();
-    untagged.serialize(serializer)
+    pub fn deserialize<'de, L, R, D>(deserializer: D) -> Result<Vec<Either<L, R>>, D::Error>
+    where
+        D: Deserializer<'de>,
+        L: DeserializeOwned,
+        R: DeserializeOwned,
+    {
+        let raw = Vec::<serde_json::Value>::deserialize(deserializer)?;
+        raw.into_iter()
+            .map(|value| {
+                serde_json::from_value::<L>(value.clone())
+                    .map(Either::Left)
+                    .or_else(|_| serde_json::from_value::<R>(value).map(Either::Right))
+                    .map_err(serde::de::Error::custom)
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -536,9 +704,13 @@ mod tests {
 
     #[test]
     fn test_null() {
-        let ast = Literal::Literal { value: Value::Null };
+        let ast = Literal::Literal {
+            value: Value::Null,
+            loc: None,
+        };
         let expected = serde_json::json!({"type": "Literal", "value": null});
-        assert_eq!(serde_json::to_value(ast).unwrap(), expected)
+        assert_eq!(serde_json::to_value(&ast).unwrap(), expected);
+        assert_eq!(serde_json::from_value::<Literal>(expected).unwrap(), ast);
     }
 
     #[test]
@@ -548,12 +720,14 @@ mod tests {
                 pattern: String::from("foo"),
                 flags: String::from("yu"),
             },
+            loc: None,
         };
         let expected = serde_json::json!({
             "type": "RegExpLiteral",
             "regex": {"pattern": "foo", "flags": "yu"},
         });
-        assert_eq!(serde_json::to_value(ast).unwrap(), expected)
+        assert_eq!(serde_json::to_value(&ast).unwrap(), expected);
+        assert_eq!(serde_json::from_value::<Literal>(expected).unwrap(), ast);
     }
 
     #[test]
@@ -562,9 +736,12 @@ mod tests {
             body: vec![Either::Left(Directive {
                 expression: Literal::Literal {
                     value: Value::String(String::from("use strict")),
+                    loc: None,
                 },
                 directive: String::from("use strict"),
+                loc: None,
             })],
+            loc: None,
         };
         let expected = serde_json::json!({
             "type": "Program",
@@ -574,14 +751,41 @@ mod tests {
                 "directive": "use strict",
             }],
         });
-        assert_eq!(serde_json::to_value(ast).unwrap(), expected)
+        assert_eq!(serde_json::to_value(&ast).unwrap(), expected);
+        assert_eq!(Program::from_json(&expected.to_string()).unwrap(), ast);
+    }
+
+    #[test]
+    fn test_expression_statement_without_directive_falls_back_to_statement() {
+        // a plain expression statement shares `Directive`'s `"ExpressionStatement"` tag and has no
+        // `directive` field to tell them apart by -- this is the case `vec_either_untagged` has to
+        // get right by trying `Directive` first and only falling back to `Statement` on failure.
+        let json = serde_json::json!({
+            "type": "Program",
+            "body": [{
+                "type": "ExpressionStatement",
+                "expression": {"type": "Identifier", "name": "x"},
+            }],
+        });
+        let program = Program::from_json(&json.to_string()).unwrap();
+        assert_eq!(
+            program.body,
+            vec![Either::Right(Statement::Expression {
+                expression: Box::new(Expression::Identifier {
+                    name: String::from("x"),
+                    loc: None,
+                }),
+                loc: None,
+            })]
+        );
     }
 
     #[test]
     fn test_empty() {
-        let ast = Statement::Empty;
+        let ast = Statement::Empty { loc: None };
         let expected = serde_json::json!({"type": "EmptyStatement"});
-        assert_eq!(serde_json::to_value(ast).unwrap(), expected)
+        assert_eq!(serde_json::to_value(&ast).unwrap(), expected);
+        assert_eq!(serde_json::from_value::<Statement>(expected).unwrap(), ast);
     }
 
     #[test]
@@ -589,16 +793,21 @@ mod tests {
         let ast = Statement::FunctionDeclaration {
             id: Identifier {
                 name: String::from("foo"),
+                loc: None,
             },
             params: vec![],
             body: FunctionBody {
                 body: vec![Either::Left(Directive {
                     expression: Literal::Literal {
                         value: Value::String(String::from("use strict")),
+                        loc: None,
                     },
                     directive: String::from("use strict"),
+                    loc: None,
                 })],
+                loc: None,
             },
+            loc: None,
         };
         let expected = serde_json::json!({
             "type": "FunctionDeclaration",
@@ -613,7 +822,8 @@ mod tests {
                 }],
             },
         });
-        assert_eq!(serde_json::to_value(ast).unwrap(), expected)
+        assert_eq!(serde_json::to_value(&ast).unwrap(), expected);
+        assert_eq!(serde_json::from_value::<Statement>(expected).unwrap(), ast);
     }
 
     #[test]
@@ -621,19 +831,24 @@ mod tests {
         let ast = Statement::Switch {
             discriminant: Box::new(Expression::Identifier {
                 name: String::from("x"),
+                loc: None,
             }),
             cases: vec![
                 SwitchCase {
                     test: Some(Box::new(Expression::Literal {
                         value: Value::Number(42.0),
+                        loc: None,
                     })),
                     consequent: vec![],
+                    loc: None,
                 },
                 SwitchCase {
                     test: None,
                     consequent: vec![],
+                    loc: None,
                 },
             ],
+            loc: None,
         };
         let expected = serde_json::json!({
             "type": "SwitchStatement",
@@ -651,7 +866,8 @@ mod tests {
                 },
             ],
         });
-        assert_eq!(serde_json::to_value(ast).unwrap(), expected)
+        assert_eq!(serde_json::to_value(&ast).unwrap(), expected);
+        assert_eq!(serde_json::from_value::<Statement>(expected).unwrap(), ast);
     }
 
     #[test]
@@ -661,19 +877,25 @@ mod tests {
                 VariableDeclarator {
                     id: Pattern::Identifier {
                         name: String::from("x"),
+                        loc: None,
                     },
                     init: None,
+                    loc: None,
                 },
                 VariableDeclarator {
                     id: Pattern::Identifier {
                         name: String::from("y"),
+                        loc: None,
                     },
                     init: Some(Box::new(Expression::Literal {
                         value: Value::Number(42.0),
+                        loc: None,
                     })),
+                    loc: None,
                 },
             ],
             kind: DeclarationKind::Var,
+            loc: None,
         };
         let expected = serde_json::json!({
             "type": "VariableDeclaration",
@@ -691,7 +913,11 @@ mod tests {
             ],
             "kind": "var",
         });
-        assert_eq!(serde_json::to_value(ast).unwrap(), expected)
+        assert_eq!(serde_json::to_value(&ast).unwrap(), expected);
+        assert_eq!(
+            serde_json::from_value::<VariableDeclaration>(expected).unwrap(),
+            ast
+        );
     }
 
     #[test]
@@ -700,29 +926,38 @@ mod tests {
             operator: AssignmentOperator::Equal,
             left: Either::Right(Box::new(Expression::Identifier {
                 name: String::from("x"),
+                loc: None,
             })),
             right: Box::new(Expression::Object {
                 properties: vec![
                     Property {
                         key: Either::Right(Identifier {
                             name: String::from("a"),
+                            loc: None,
                         }),
                         value: Box::new(Expression::Literal {
                             value: Value::Number(1.0),
+                            loc: None,
                         }),
                         kind: PropertyKind::Init,
+                        loc: None,
                     },
                     Property {
                         key: Either::Left(Literal::Literal {
                             value: Value::String(String::from("b")),
+                            loc: None,
                         }),
                         value: Box::new(Expression::Identifier {
                             name: String::from("y"),
+                            loc: None,
                         }),
                         kind: PropertyKind::Init,
+                        loc: None,
                     },
                 ],
+                loc: None,
             }),
+            loc: None,
         };
         let expected = serde_json::json!({
             "type": "AssignmentExpression",
@@ -746,7 +981,31 @@ mod tests {
                 ]
             }
         });
-        assert_eq!(serde_json::to_value(ast).unwrap(), expected)
+        assert_eq!(serde_json::to_value(&ast).unwrap(), expected);
+        // `{"type": "Identifier", "name": "x"}` is just as valid a `Pattern` as an `Expression`, and
+        // `either::serde_untagged` tries the `Left` (`Pattern`) side first, so that's what `left`
+        // reads back as
+        let round_tripped = match ast {
+            Expression::Assignment {
+                operator,
+                right,
+                loc,
+                ..
+            } => Expression::Assignment {
+                operator,
+                left: Either::Left(Pattern::Identifier {
+                    name: String::from("x"),
+                    loc: None,
+                }),
+                right,
+                loc,
+            },
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            serde_json::from_value::<Expression>(expected).unwrap(),
+            round_tripped
+        );
     }
 
     #[test]
@@ -755,10 +1014,13 @@ mod tests {
             operator: BinaryOperator::NotTripleEqual,
             left: Box::new(Expression::Literal {
                 value: Value::Number(1.0),
+                loc: None,
             }),
             right: Box::new(Expression::Literal {
                 value: Value::String(String::from("1")),
+                loc: None,
             }),
+            loc: None,
         };
         let expected = serde_json::json!({
             "type": "BinaryExpression",
@@ -766,6 +1028,10 @@ mod tests {
             "left": {"type": "Literal", "value": 1.0},
             "right": {"type": "Literal", "value": "1"},
         });
-        assert_eq!(serde_json::to_value(ast).unwrap(), expected)
+        assert_eq!(serde_json::to_value(&ast).unwrap(), expected);
+        assert_eq!(
+            serde_json::from_value::<Expression>(expected).unwrap(),
+            ast
+        );
     }
 }
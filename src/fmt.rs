@@ -0,0 +1,44 @@
+// a canonical pretty-printer over the tree-sitter CST, not the higher-level `syntax` AST, so that
+// comments -- which `syntax::File::make` has no representation for -- survive formatting. Shared
+// by `quench fmt` and the LSP's `textDocument/formatting` provider (see `db::QueryGroup::formatted`).
+
+use tree_sitter::Node;
+
+fn format_node(node: &Node, text: &str, out: &mut String) {
+    match node.kind() {
+        "call" => {
+            let function = node.child_by_field_name("function").unwrap();
+            format_node(&function, text, out);
+            out.push('(');
+            if let Some(arguments) = node.child_by_field_name("arguments") {
+                let mut cursor = arguments.walk();
+                for (i, argument) in arguments.named_children(&mut cursor).enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    format_node(&argument, text, out);
+                }
+            }
+            out.push(')');
+        }
+        // identifiers, strings, and anything else we don't have a canonical shape for yet are
+        // just emitted verbatim
+        _ => out.push_str(node.utf8_text(text.as_bytes()).unwrap_or("")),
+    }
+}
+
+/// Formats `root`'s top-level children, one per line: each statement is reconstructed from its
+/// parsed shape and terminated with `;`, while comments are kept verbatim on their own line, all
+/// in source order.
+pub fn format(root: &Node, text: &str) -> String {
+    let mut out = String::new();
+    let mut cursor = root.walk();
+    for child in root.named_children(&mut cursor) {
+        format_node(&child, text, &mut out);
+        if child.kind() != "comment" {
+            out.push(';');
+        }
+        out.push('\n');
+    }
+    out
+}
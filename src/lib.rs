@@ -0,0 +1,18 @@
+pub mod backend;
+pub mod codegen;
+pub mod compiler;
+pub mod db;
+pub mod estree;
+pub mod fmt;
+pub mod llvm;
+pub mod loader;
+pub mod lockfile;
+mod parser;
+pub mod runtime;
+pub mod semantics;
+pub mod sourcemap;
+pub mod syntax;
+pub mod test_runner;
+mod text;
+pub mod types;
+pub mod vfs;
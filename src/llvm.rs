@@ -0,0 +1,424 @@
+// a native-code backend: lowers the same typed IR `compiler::compile_file` targets at `estree`
+// into LLVM IR via `inkwell`, for callers that want an ahead-of-time compiled binary (or a JIT run)
+// with no JS runtime (Deno) dependency. Loosely mirrors the shape of achilles' own
+// `codegen/llvm.rs`: one `Context` per compilation, `f64`/`i1` for `Number`/`Boolean` (quench has no
+// integer type yet, so unlike achilles there's no separate `i64` path), locals as `alloca`s the way
+// the classic "Kaleidoscope" tutorial does it (simplest way to support `let` reassignment without a
+// separate mutability analysis), and either a `TargetMachine` (for an object file) or a JIT
+// `ExecutionEngine` (for running `main` directly, no linker involved) to actually produce something
+// runnable.
+//
+// `String` has no representation here -- there's no runtime to back a string type with -- so a file
+// that reaches this backend with a `String`-typed literal, `let`, or parameter simply isn't
+// supported yet, and compiling it returns `None` rather than an error (unlike `estree`, there's no
+// `compiler::CompileError` equivalent here -- see that module for why the two backends differ).
+// Calls are native-to-native only: `print`/`args`/`test` are Deno/JS builtins `compiler` recognizes
+// by name, and have no counterpart a native binary could call into, so a call to any of them is
+// unsupported here too.
+
+use crate::{syntax, types};
+use inkwell::{
+    builder::Builder,
+    context::Context,
+    module::Module,
+    targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine},
+    types::BasicTypeEnum,
+    values::{BasicValueEnum, FunctionValue, PointerValue},
+    FloatPredicate, OptimizationLevel,
+};
+use std::{collections::HashMap, path::Path};
+
+/// Maps a quench `Type` to the LLVM type this backend represents it as. A bare `Type::Var` can
+/// still reach this backend -- the recorded `ty` of a `let` without an initializer is the fresh
+/// variable it started with, even once a later assignment narrows it, and a parameter nothing in
+/// its function's body constrains stays a variable -- and it maps to `None` (unsupported), as do
+/// `String` and `Null`; `Fun` doesn't appear as a value type, since this backend calls functions
+/// directly by name rather than ever treating one as a value.
+fn llvm_type<'ctx>(context: &'ctx Context, ty: &types::Type) -> Option<BasicTypeEnum<'ctx>> {
+    match ty {
+        types::Type::Number => Some(context.f64_type().into()),
+        types::Type::Boolean => Some(context.bool_type().into()),
+        types::Type::Null | types::Type::String | types::Type::Fun(..) | types::Type::Var(_) => None,
+    }
+}
+
+/// Per-function compilation state: the `alloca`s backing each `let`/parameter currently in scope,
+/// so an `Assign` can find the slot to store back into. An `if`/`while` body is compiled against
+/// this map as it stood before the body, restored once the body is done, so (as in
+/// `semantics::check`) a `let` inside the body neither escapes it nor replaces an enclosing binding
+/// of the same name; its `alloca` still lives in the function's frame, like every `alloca` in LLVM.
+struct FunctionCompiler<'a, 'ctx> {
+    context: &'ctx Context,
+    builder: &'a Builder<'ctx>,
+    module: &'a Module<'ctx>,
+    function: FunctionValue<'ctx>,
+    locals: HashMap<String, PointerValue<'ctx>>,
+}
+
+impl<'a, 'ctx> FunctionCompiler<'a, 'ctx> {
+    fn declare_local(&mut self, name: &str, ty: BasicTypeEnum<'ctx>, value: BasicValueEnum<'ctx>) {
+        let slot = self.builder.build_alloca(ty, name);
+        self.builder.build_store(slot, value);
+        self.locals.insert(String::from(name), slot);
+    }
+
+    /// Whether the block the builder is currently positioned at already ends in a terminator (a
+    /// `Return` compiled somewhere inside it, most likely) -- a block can only have one, so callers
+    /// use this to decide whether falling through to the next block is still necessary.
+    fn current_block_is_terminated(&self) -> bool {
+        self.builder
+            .get_insert_block()
+            .and_then(|block| block.get_terminator())
+            .is_some()
+    }
+
+    fn compile_expression(&mut self, expr: &types::Expression) -> Option<BasicValueEnum<'ctx>> {
+        match expr {
+            types::Expression::Lit { value, .. } => match value {
+                types::Literal::Num(value) => Some(self.context.f64_type().const_float(*value).into()),
+                types::Literal::Bool(value) => {
+                    Some(self.context.bool_type().const_int(*value as u64, false).into())
+                }
+                types::Literal::Str(_) => None,
+            },
+            types::Expression::Id { name, .. } => {
+                let slot = self.locals.get(name)?;
+                Some(self.builder.build_load(*slot, name))
+            }
+            types::Expression::Call { function, arguments, .. } => {
+                let callee = self.module.get_function(function)?;
+                let mut compiled_arguments = vec![];
+                for argument in arguments {
+                    compiled_arguments.push(self.compile_expression(argument)?.into());
+                }
+                self.builder
+                    .build_call(callee, &compiled_arguments, "call")
+                    .try_as_basic_value()
+                    .left()
+            }
+            types::Expression::Binary { op, left, right, .. } => {
+                let left = self.compile_expression(left)?.into_float_value();
+                let right = self.compile_expression(right)?.into_float_value();
+                Some(match op {
+                    syntax::BinaryOp::Add => self.builder.build_float_add(left, right, "add").into(),
+                    syntax::BinaryOp::Subtract => self.builder.build_float_sub(left, right, "sub").into(),
+                    syntax::BinaryOp::Multiply => self.builder.build_float_mul(left, right, "mul").into(),
+                    syntax::BinaryOp::Divide => self.builder.build_float_div(left, right, "div").into(),
+                    syntax::BinaryOp::Modulus => self.builder.build_float_rem(left, right, "rem").into(),
+                    syntax::BinaryOp::Equal => self
+                        .builder
+                        .build_float_compare(FloatPredicate::OEQ, left, right, "eq")
+                        .into(),
+                    syntax::BinaryOp::NotEqual => self
+                        .builder
+                        .build_float_compare(FloatPredicate::ONE, left, right, "ne")
+                        .into(),
+                    syntax::BinaryOp::Less => self
+                        .builder
+                        .build_float_compare(FloatPredicate::OLT, left, right, "lt")
+                        .into(),
+                    syntax::BinaryOp::LessEqual => self
+                        .builder
+                        .build_float_compare(FloatPredicate::OLE, left, right, "le")
+                        .into(),
+                    syntax::BinaryOp::Greater => self
+                        .builder
+                        .build_float_compare(FloatPredicate::OGT, left, right, "gt")
+                        .into(),
+                    syntax::BinaryOp::GreaterEqual => self
+                        .builder
+                        .build_float_compare(FloatPredicate::OGE, left, right, "ge")
+                        .into(),
+                })
+            }
+            // short-circuits for real, via a branch and a `phi`, rather than lowering to `build_and`/
+            // `build_or` on the two eagerly-evaluated operands -- `right` may have side effects
+            // (a call, an assignment), and JS (quench's only other target) never evaluates it unless
+            // the left side didn't already decide the result
+            types::Expression::Logical { op, left, right, .. } => {
+                let left = self.compile_expression(left)?.into_int_value();
+
+                let right_block = self.context.append_basic_block(self.function, "logical.rhs");
+                let merge_block = self.context.append_basic_block(self.function, "logical.merge");
+                let left_block = self.builder.get_insert_block().unwrap();
+
+                match op {
+                    syntax::LogicalOp::And => self
+                        .builder
+                        .build_conditional_branch(left, right_block, merge_block),
+                    syntax::LogicalOp::Or => self
+                        .builder
+                        .build_conditional_branch(left, merge_block, right_block),
+                };
+
+                self.builder.position_at_end(right_block);
+                let right = self.compile_expression(right)?.into_int_value();
+                self.builder.build_unconditional_branch(merge_block);
+                let right_block = self.builder.get_insert_block().unwrap();
+
+                self.builder.position_at_end(merge_block);
+                let phi = self.builder.build_phi(self.context.bool_type(), "logical.result");
+                phi.add_incoming(&[(&left, left_block), (&right, right_block)]);
+                Some(phi.as_basic_value())
+            }
+            types::Expression::Assign { name, op, value, .. } => {
+                let slot = *self.locals.get(name)?;
+                let value = self.compile_expression(value)?.into_float_value();
+                let result = match op {
+                    syntax::AssignOp::Equal => value,
+                    syntax::AssignOp::AddEqual | syntax::AssignOp::SubtractEqual => {
+                        let current = self.builder.build_load(slot, name).into_float_value();
+                        if matches!(op, syntax::AssignOp::AddEqual) {
+                            self.builder.build_float_add(current, value, "add")
+                        } else {
+                            self.builder.build_float_sub(current, value, "sub")
+                        }
+                    }
+                    syntax::AssignOp::MultiplyEqual | syntax::AssignOp::DivideEqual => {
+                        let current = self.builder.build_load(slot, name).into_float_value();
+                        if matches!(op, syntax::AssignOp::MultiplyEqual) {
+                            self.builder.build_float_mul(current, value, "mul")
+                        } else {
+                            self.builder.build_float_div(current, value, "div")
+                        }
+                    }
+                };
+                self.builder.build_store(slot, result);
+                Some(result.into())
+            }
+        }
+    }
+
+    /// Compiles one statement. Returns `None` the moment anything inside it turns out to be
+    /// unsupported -- this backend has real gaps (see the module doc comment), unlike `estree`'s
+    /// `compiler::compile_statement`, which reports those via `CompileError` instead.
+    fn compile_statement(&mut self, stmt: &types::Statement) -> Option<()> {
+        match stmt {
+            types::Statement::Expr(expr) => {
+                self.compile_expression(expr)?;
+                Some(())
+            }
+            types::Statement::Let { name, ty, init, .. } => {
+                let llvm_ty = llvm_type(self.context, ty)?;
+                let value = match init {
+                    Some(init) => self.compile_expression(init)?,
+                    None => match ty {
+                        types::Type::Number => self.context.f64_type().const_zero().into(),
+                        types::Type::Boolean => self.context.bool_type().const_zero().into(),
+                        _ => return None,
+                    },
+                };
+                self.declare_local(name, llvm_ty, value);
+                Some(())
+            }
+            types::Statement::If {
+                test,
+                consequent,
+                alternate,
+                ..
+            } => {
+                let test = self.compile_expression(test)?.into_int_value();
+
+                let then_block = self.context.append_basic_block(self.function, "if.then");
+                let else_block = self.context.append_basic_block(self.function, "if.else");
+                let after_block = self.context.append_basic_block(self.function, "if.after");
+
+                self.builder.build_conditional_branch(test, then_block, else_block);
+
+                self.builder.position_at_end(then_block);
+                let outer_locals = self.locals.clone();
+                for stmt in consequent {
+                    self.compile_statement(stmt)?;
+                }
+                self.locals = outer_locals.clone();
+                // a `return` inside the branch already left it with a terminator -- branching to
+                // `after_block` on top of that would leave the block with two, which LLVM's
+                // verifier rejects
+                if !self.current_block_is_terminated() {
+                    self.builder.build_unconditional_branch(after_block);
+                }
+
+                self.builder.position_at_end(else_block);
+                if let Some(alternate) = alternate {
+                    for stmt in alternate {
+                        self.compile_statement(stmt)?;
+                    }
+                }
+                self.locals = outer_locals;
+                if !self.current_block_is_terminated() {
+                    self.builder.build_unconditional_branch(after_block);
+                }
+
+                self.builder.position_at_end(after_block);
+                Some(())
+            }
+            types::Statement::While { test, body, .. } => {
+                let test_block = self.context.append_basic_block(self.function, "while.test");
+                let body_block = self.context.append_basic_block(self.function, "while.body");
+                let after_block = self.context.append_basic_block(self.function, "while.after");
+
+                self.builder.build_unconditional_branch(test_block);
+                self.builder.position_at_end(test_block);
+                let test = self.compile_expression(test)?.into_int_value();
+                self.builder.build_conditional_branch(test, body_block, after_block);
+
+                self.builder.position_at_end(body_block);
+                let outer_locals = self.locals.clone();
+                for stmt in body {
+                    self.compile_statement(stmt)?;
+                }
+                self.locals = outer_locals;
+                // same as the `if` arm above: a `return` inside the body already terminated this
+                // block, so looping back to `test_block` would add a second terminator
+                if !self.current_block_is_terminated() {
+                    self.builder.build_unconditional_branch(test_block);
+                }
+
+                self.builder.position_at_end(after_block);
+                Some(())
+            }
+            types::Statement::Return { value, .. } => {
+                match value {
+                    Some(value) => {
+                        let value = self.compile_expression(value)?;
+                        self.builder.build_return(Some(&value));
+                    }
+                    None => {
+                        self.builder.build_return(None);
+                    }
+                }
+                Some(())
+            }
+            // nested `function` declarations aren't hoisted out to their own LLVM function yet --
+            // only the top-level ones `compile_file` walks before building `main` are -- so one
+            // showing up inside an `if`/`while`/function body here just isn't supported
+            types::Statement::FunctionDecl { .. } => None,
+        }
+    }
+}
+
+/// Declares and defines `name` as its own LLVM function, with `params` (already typed by
+/// `types::infer`) as its parameters and `ret` as its return type.
+fn compile_function<'ctx>(
+    context: &'ctx Context,
+    builder: &Builder<'ctx>,
+    module: &Module<'ctx>,
+    name: &str,
+    params: &[(String, types::Type)],
+    ret: &types::Type,
+    body: &[types::Statement],
+) -> Option<FunctionValue<'ctx>> {
+    let param_types: Vec<_> = params
+        .iter()
+        .map(|(_, ty)| llvm_type(context, ty).map(Into::into))
+        .collect::<Option<_>>()?;
+    let fn_type = match llvm_type(context, ret) {
+        Some(ret) => ret.fn_type(&param_types, false),
+        None if matches!(ret, types::Type::Null) => context.void_type().fn_type(&param_types, false),
+        None => return None,
+    };
+
+    let function = module.add_function(name, fn_type, None);
+    let entry = context.append_basic_block(function, "entry");
+    builder.position_at_end(entry);
+
+    let mut compiler = FunctionCompiler {
+        context,
+        builder,
+        module,
+        function,
+        locals: HashMap::new(),
+    };
+    for (i, (param_name, param_ty)) in params.iter().enumerate() {
+        let llvm_ty = llvm_type(context, param_ty)?;
+        compiler.declare_local(param_name, llvm_ty, function.get_nth_param(i as u32)?);
+    }
+    for stmt in body {
+        compiler.compile_statement(stmt)?;
+    }
+    // a `Null`-returning function may fall off the end of its body without a `return`
+    if matches!(ret, types::Type::Null) && !compiler.current_block_is_terminated() {
+        builder.build_return(None);
+    }
+
+    Some(function)
+}
+
+/// Compiles every top-level statement in `file` into a single `"main"` LLVM function returning
+/// `i32` (always `0` -- quench scripts don't have an exit-code concept of their own yet), with any
+/// top-level `function` declaration compiled first so `main` can call it. Doesn't verify every
+/// control-flow path through a non-`Null`-returning function actually ends in a `return`; a file
+/// that skips one will fail LLVM's own module verifier rather than this function, a gap shared by
+/// plenty of bare-bones front ends and left for a later pass (`semantics::check`, most likely) to
+/// close.
+pub fn compile_file<'ctx>(context: &'ctx Context, file: &types::File) -> Option<Module<'ctx>> {
+    let module = context.create_module("quench");
+    let builder = context.create_builder();
+
+    for stmt in &file.body {
+        if let types::Statement::FunctionDecl {
+            name, params, ret, body, ..
+        } = stmt
+        {
+            compile_function(context, &builder, &module, name, params, ret, body)?;
+        }
+    }
+
+    let main_type = context.i32_type().fn_type(&[], false);
+    let main_function = module.add_function("main", main_type, None);
+    let entry = context.append_basic_block(main_function, "entry");
+    builder.position_at_end(entry);
+
+    let mut compiler = FunctionCompiler {
+        context,
+        builder: &builder,
+        module: &module,
+        function: main_function,
+        locals: HashMap::new(),
+    };
+    for stmt in &file.body {
+        if matches!(stmt, types::Statement::FunctionDecl { .. }) {
+            continue;
+        }
+        compiler.compile_statement(stmt)?;
+    }
+    builder.build_return(Some(&context.i32_type().const_int(0, false)));
+
+    Some(module)
+}
+
+/// Emits `module` as a native object file at `out`, suitable for linking into an executable with
+/// the system linker (`cc`, `lld`, ...) -- this backend doesn't drive a linker itself, so turning
+/// the result into something directly runnable is left to the caller.
+pub fn emit_object(module: &Module, out: &Path) -> anyhow::Result<()> {
+    Target::initialize_native(&InitializationConfig::default()).map_err(|message| anyhow::anyhow!(message))?;
+
+    let triple = TargetMachine::get_default_triple();
+    let target = Target::from_triple(&triple).map_err(|error| anyhow::anyhow!(error.to_string()))?;
+    let machine = target
+        .create_target_machine(
+            &triple,
+            &TargetMachine::get_host_cpu_name().to_string(),
+            &TargetMachine::get_host_cpu_features().to_string(),
+            OptimizationLevel::Default,
+            RelocMode::Default,
+            CodeModel::Default,
+        )
+        .ok_or_else(|| anyhow::anyhow!("couldn't create a target machine for {}", triple))?;
+
+    machine
+        .write_to_file(module, FileType::Object, out)
+        .map_err(|error| anyhow::anyhow!(error.to_string()))
+}
+
+/// JIT-executes `module`'s `main` and returns the `i32` it returns -- the path `quench run
+/// --target native` takes, skipping `emit_object` (and thus a linker) entirely.
+pub fn jit_run(module: Module) -> anyhow::Result<i32> {
+    let engine = module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+    unsafe {
+        let main = engine.get_function::<unsafe extern "C" fn() -> i32>("main")?;
+        Ok(main.call())
+    }
+}
@@ -1,8 +1,18 @@
+use crate::lockfile::{LockMode, Lockfile};
 use deno_core::{
     error::AnyError, ModuleLoader, ModuleSource, ModuleSourceFuture, ModuleSpecifier, OpState,
 };
 use futures::future::FutureExt;
-use std::{cell::RefCell, pin::Pin, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
 use url::Url;
 
 #[derive(Debug, thiserror::Error)]
@@ -11,9 +21,95 @@ pub struct LoadError {
     module_specifier: ModuleSpecifier,
 }
 
+const IMMUTABLE_URL: &str = "https://deno.land/x/immutable@4.0.0-rc.12-deno/mod.ts";
+const IMMUTABLE_SOURCE: &str =
+    include_str!("../jsdeps/node_modules/immutable/dist/immutable.es.js");
+const IMMUTABLE_FOUND_URL: &str = concat!(
+    "https://github.com/quench-lang/quench/raw/",
+    env!("VERGEN_GIT_SHA"),
+    "/jsdeps/node_modules/immutable/dist/immutable.es.js",
+);
+
+fn cache_path(cache_dir: &Path, url: &Url) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    url.as_str().hash(&mut hasher);
+    cache_dir.join(format!("{:016x}", hasher.finish()))
+}
+
+// downloads `url` over the network, unless a cached copy already exists on disk (or `url` is the
+// built-in pinned `immutable` bundle, which never touches the network); writes whatever it
+// resolves to back into the cache so later loads of the same URL are free, then checks the result
+// against `lock`, if one was configured
+async fn load_remote(
+    cache_dir: &Path,
+    force_reload: bool,
+    lock: Option<&LockConfig>,
+    url: &Url,
+) -> Result<String, AnyError> {
+    let path = cache_path(cache_dir, url);
+    let cached = if force_reload {
+        None
+    } else {
+        fs::read_to_string(&path).ok()
+    };
+    let code = match cached {
+        Some(code) => code,
+        None => {
+            let code = if url.as_str() == IMMUTABLE_URL {
+                IMMUTABLE_SOURCE.to_string()
+            } else {
+                reqwest::get(url.clone()).await?.text().await?
+            };
+            fs::create_dir_all(cache_dir)?;
+            fs::write(&path, &code)?;
+            code
+        }
+    };
+
+    if let Some(lock) = lock {
+        lock.lockfile
+            .lock()
+            .unwrap()
+            .check(lock.mode, url, code.as_bytes())?;
+    }
+
+    Ok(code)
+}
+
+/// A `--lock`/`--lock-write` configuration shared across every module a `FixedLoader` resolves;
+/// wrapped in a mutex because `ModuleLoader::load` only gets `&self` and deno_core may resolve
+/// several modules concurrently.
+#[derive(Clone)]
+pub struct LockConfig {
+    pub mode: LockMode,
+    pub lockfile: Arc<Mutex<Lockfile>>,
+}
+
+/// Resolves `https://` module specifiers by downloading them over the network the first time
+/// they're seen and caching the bytes on disk afterward, keyed by a hash of the URL, the way
+/// `deno`'s module cache works. The pinned `immutable` bundle is served from a built-in constant
+/// rather than the network, but still flows through the same on-disk cache so it isn't a special
+/// case for callers. When `lock` is set, every resolved module's bytes are checked against (or, in
+/// `LockMode::Write`, recorded into) the lockfile.
 pub struct FixedLoader {
     pub main_module: Url,
     pub main_source: String,
+    pub cache_dir: PathBuf,
+    /// bypass the on-disk cache and re-fetch every remote module, even if already cached
+    pub force_reload: bool,
+    pub lock: Option<LockConfig>,
+}
+
+impl FixedLoader {
+    pub fn new(main_module: Url, main_source: String) -> Self {
+        FixedLoader {
+            main_module,
+            main_source,
+            cache_dir: PathBuf::from(".quench_cache"),
+            force_reload: false,
+            lock: None,
+        }
+    }
 }
 
 impl ModuleLoader for FixedLoader {
@@ -37,29 +133,34 @@ impl ModuleLoader for FixedLoader {
         let main_module = self.main_module.clone();
         let main_source = self.main_source.clone(); // TODO
         let module_specifier = module_specifier.clone();
+        let cache_dir = self.cache_dir.clone();
+        let force_reload = self.force_reload;
+        let lock = self.lock.clone();
         async move {
-            let specifier_str = module_specifier.as_str();
-            if specifier_str == "https://deno.land/x/immutable@4.0.0-rc.12-deno/mod.ts" {
-                Ok(ModuleSource {
-                    code: include_str!("../jsdeps/node_modules/immutable/dist/immutable.es.js")
-                        .to_string(),
-                    module_url_specified: module_specifier.to_string(),
-                    module_url_found: concat!(
-                        "https://github.com/quench-lang/quench/raw/",
-                        env!("VERGEN_GIT_SHA"),
-                        "/jsdeps/node_modules/immutable/dist/immutable.es.js",
-                    )
-                    .to_string(),
-                })
-            } else if specifier_str == main_module.as_str() {
-                Ok(ModuleSource {
+            if module_specifier.as_str() == main_module.as_str() {
+                return Ok(ModuleSource {
                     code: main_source,
                     module_url_specified: module_specifier.to_string(),
                     module_url_found: main_module.to_string(),
-                })
-            } else {
-                Err(LoadError { module_specifier })?
+                });
+            }
+
+            if module_specifier.scheme() != "https" {
+                Err(LoadError { module_specifier })?;
             }
+
+            let code =
+                load_remote(&cache_dir, force_reload, lock.as_ref(), &module_specifier).await?;
+            let module_url_found = if module_specifier.as_str() == IMMUTABLE_URL {
+                IMMUTABLE_FOUND_URL.to_string()
+            } else {
+                module_specifier.to_string()
+            };
+            Ok(ModuleSource {
+                code,
+                module_url_specified: module_specifier.to_string(),
+                module_url_found,
+            })
         }
         .boxed_local()
     }
@@ -0,0 +1,119 @@
+// a `quench.lock`-backed integrity check for remote modules, mirroring how `deno` pins a
+// program's dependencies so a run fetches (or trusts a cache of) byte-identical source; see
+// `loader::FixedLoader`, which calls into this after resolving each module's bytes
+
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+use url::Url;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LockfileError {
+    #[error(
+        "integrity check failed for {url}: lockfile has {expected}, but downloaded content hashes to {actual}"
+    )]
+    IntegrityMismatch {
+        url: Url,
+        expected: String,
+        actual: String,
+    },
+    #[error("{url} is not in the lockfile; run with --lock-write to add it")]
+    NotLocked { url: Url },
+}
+
+fn hash(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("sha256-{:x}", hasher.finalize())
+}
+
+/// `--lock` verifies every module against an existing lockfile entry, erroring on anything
+/// unrecognized; `--lock-write` additionally records a hash for a module seen for the first time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    Verify,
+    Write,
+}
+
+/// Per-module integrity hashes, persisted as JSON mapping a module's URL to a `sha256-<hex>`
+/// digest of its contents.
+#[derive(Debug, Default)]
+pub struct Lockfile {
+    path: PathBuf,
+    entries: BTreeMap<String, String>,
+    dirty: bool,
+}
+
+impl Lockfile {
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Lockfile {
+            path,
+            entries,
+            dirty: false,
+        }
+    }
+
+    /// Verifies `bytes` against the recorded hash for `url`, or (in `LockMode::Write`) records
+    /// `url`'s hash if this is the first time it's been seen.
+    pub fn check(&mut self, mode: LockMode, url: &Url, bytes: &[u8]) -> Result<(), LockfileError> {
+        let actual = hash(bytes);
+        match self.entries.get(url.as_str()) {
+            Some(expected) if *expected == actual => Ok(()),
+            Some(expected) => Err(LockfileError::IntegrityMismatch {
+                url: url.clone(),
+                expected: expected.clone(),
+                actual,
+            }),
+            None if mode == LockMode::Write => {
+                self.entries.insert(url.to_string(), actual);
+                self.dirty = true;
+                Ok(())
+            }
+            None => Err(LockfileError::NotLocked { url: url.clone() }),
+        }
+    }
+
+    pub fn write(&self) -> std::io::Result<()> {
+        if self.dirty {
+            let contents = serde_json::to_string_pretty(&self.entries).unwrap();
+            fs::write(&self.path, contents)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_tampered_cache_is_rejected_under_verify() {
+        let mut lockfile = Lockfile::default();
+        let module = url("https://example.com/mod.ts");
+        lockfile
+            .check(LockMode::Write, &module, b"export const answer = 42;")
+            .unwrap();
+
+        let error = lockfile
+            .check(LockMode::Verify, &module, b"export const answer = 0;")
+            .unwrap_err();
+        assert!(matches!(error, LockfileError::IntegrityMismatch { .. }));
+    }
+
+    #[test]
+    fn test_unrecognized_module_is_rejected_under_verify() {
+        let mut lockfile = Lockfile::default();
+        let error = lockfile
+            .check(LockMode::Verify, &url("https://example.com/mod.ts"), b"anything")
+            .unwrap_err();
+        assert!(matches!(error, LockfileError::NotLocked { .. }));
+    }
+}
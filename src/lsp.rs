@@ -1,10 +1,11 @@
 use lspower::{
     jsonrpc::{Error, ErrorCode, Result},
-    lsp::*,
+    lsp::{notification, request, *},
     Client, LanguageServer, LspService, Server,
 };
 use quench::db;
 use std::sync::Arc;
+use tokio::sync::mpsc;
 
 enum ServerErrorCode {
     // https://microsoft.github.io/language-server-protocol/specifications/specification-3-16/#responseMessage
@@ -17,13 +18,106 @@ struct Backend {
     state: Arc<db::State>,
 }
 
+// relays `db::Progress` values from the worker pool onto the async LSP connection as `$/progress`
+// notifications, so a long "index the workspace" pass gives the user a spinner/percentage instead
+// of going quiet until diagnostics show up
+async fn relay_progress(client: Client, token: NumberOrString, mut rx: mpsc::Receiver<db::Progress>) {
+    while let Some(progress) = rx.recv().await {
+        let value = match progress {
+            db::Progress::Begin { title } => WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title,
+                cancellable: Some(false),
+                message: None,
+                percentage: Some(0),
+            }),
+            db::Progress::Report { done, total } => {
+                WorkDoneProgress::Report(WorkDoneProgressReport {
+                    cancellable: Some(false),
+                    message: Some(format!("{} / {}", done, total)),
+                    percentage: Some((done * 100 / total.max(1)) as u32),
+                })
+            }
+            db::Progress::End => WorkDoneProgress::End(WorkDoneProgressEnd { message: None }),
+        };
+        client
+            .send_notification::<notification::Progress>(ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(value),
+            })
+            .await;
+    }
+}
+
 #[lspower::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let supports_progress = params
+            .capabilities
+            .window
+            .as_ref()
+            .and_then(|window| window.work_done_progress)
+            .unwrap_or(false);
+
+        if let Some(root) = params.root_uri.and_then(|uri| uri.to_file_path().ok()) {
+            let state = self.state.clone();
+            let client = self.client.clone();
+            tokio::spawn(async move {
+                let progress = if supports_progress {
+                    let token = NumberOrString::String(String::from("quench/indexWorkspace"));
+                    let created = client
+                        .send_request::<request::WorkDoneProgressCreate>(
+                            WorkDoneProgressCreateParams {
+                                token: token.clone(),
+                            },
+                        )
+                        .await;
+                    created.ok().map(|()| {
+                        let (tx, rx) = mpsc::channel(16);
+                        tokio::spawn(relay_progress(client.clone(), token, rx));
+                        tx
+                    })
+                } else {
+                    None
+                };
+
+                let files = quench::vfs::scan(&root);
+                // best-effort: if this races with shutdown the oneshot reply is just dropped
+                let _ = state.index_workspace(files).await;
+                if let Ok(diagnostics) = state.diagnose_workspace(progress).await {
+                    for (uri, diagnostics) in diagnostics {
+                        client.publish_diagnostics(uri, diagnostics, None).await;
+                    }
+                }
+
+                if let Ok((watcher, rx)) = quench::vfs::watch(&root) {
+                    let handle = tokio::runtime::Handle::current();
+                    tokio::task::spawn_blocking(move || {
+                        // keep the watcher alive for as long as we're receiving from it
+                        let _watcher = watcher;
+                        for change in rx {
+                            let result = match change {
+                                quench::vfs::Change::Updated(uri, text) => {
+                                    handle.block_on(state.update_workspace_file(uri, text))
+                                }
+                                quench::vfs::Change::Removed(uri) => {
+                                    handle.block_on(state.remove_workspace_file(uri))
+                                }
+                            };
+                            let _ = result;
+                        }
+                    });
+                }
+            });
+        }
+
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
+                // db::State::edit_document already applies each TextDocumentContentChangeEvent's
+                // range to the cached buffer and incrementally reparses via tree-sitter's
+                // Tree::edit, so advertising Incremental here is enough to get the client sending
+                // small diffs instead of the whole document on every keystroke
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::Full,
+                    TextDocumentSyncKind::Incremental,
                 )),
                 semantic_tokens_provider: Some(
                     SemanticTokensServerCapabilities::SemanticTokensOptions(
@@ -40,6 +134,7 @@ impl LanguageServer for Backend {
                         },
                     ),
                 ),
+                document_formatting_provider: Some(OneOf::Left(true)),
                 ..ServerCapabilities::default()
             },
             server_info: None,
@@ -94,6 +189,27 @@ impl LanguageServer for Backend {
             data: tokens,
         })))
     }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri;
+        let formatted = self
+            .state
+            .get_formatted(uri.clone())
+            .await
+            .map_err(|_| Error {
+                code: ErrorCode::ServerError(ServerErrorCode::DocNotInCache as i64),
+                message: format!("URI not in document cache: {}", uri),
+                data: None,
+            })?;
+        // a single edit replacing the whole document is simpler than diffing old vs. new text, and
+        // every client we care about collapses it to a no-op range replace when nothing changed
+        Ok(formatted.map(|formatted| {
+            vec![TextEdit {
+                range: Range::new(Position::new(0, 0), Position::new(u32::MAX, 0)),
+                new_text: (*formatted).clone(),
+            }]
+        }))
+    }
 }
 
 #[tokio::main]
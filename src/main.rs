@@ -1,6 +1,18 @@
+use inkwell::context::Context;
 use lspower::lsp;
-use quench::db::{self, QueryGroup};
-use std::path::PathBuf;
+use quench::{
+    backend::{Backend, Target},
+    codegen, compiler,
+    db::{self, QueryGroup},
+    llvm,
+    loader::{FixedLoader, LockConfig},
+    lockfile::{LockMode, Lockfile},
+    runtime, types,
+};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 use structopt::StructOpt;
 use url::Url;
 
@@ -29,19 +41,199 @@ As you can see, Quench can parse your program, but can't run it yet. Stay tuned!
 #[derive(Debug, StructOpt)]
 #[structopt(about = ABOUT)]
 struct Opt {
-    /// Source file to run as a script
-    file: PathBuf,
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
+
+    /// Source file to run as a script (ignored if a subcommand is given)
+    file: Option<PathBuf>,
 
     /// Arguments to pass to the script
     args: Vec<String>,
 }
 
-fn main() -> anyhow::Result<()> {
-    let opt = Opt::from_args();
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Discover and run `*_test.qn` files, printing a pass/fail summary
+    Test {
+        /// Only report (and count toward the exit code) test cases whose name contains this
+        /// substring; every case in every discovered file still runs
+        #[structopt(long)]
+        filter: Option<String>,
+
+        /// Directory to search for test files
+        #[structopt(default_value = ".")]
+        root: PathBuf,
+    },
+
+    /// Format a source file in place, or with --check, report whether it's already formatted
+    Fmt {
+        file: PathBuf,
+
+        /// Don't write anything; exit nonzero if the file isn't already formatted
+        #[structopt(long)]
+        check: bool,
+    },
+
+    /// Compile a source file to a standalone artifact without running it
+    Compile {
+        file: PathBuf,
+
+        /// `js` (run under Deno) or `native` (an LLVM-compiled object file)
+        #[structopt(long, default_value = "js")]
+        target: Target,
+
+        /// Where to write the compiled artifact (defaults to the input file with its extension
+        /// swapped for the target's: `.js` or `.o`)
+        #[structopt(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Compile and run a source file
+    Run {
+        file: PathBuf,
+
+        /// `js` (run under Deno) or `native` (JIT-executed, no Deno and no object file involved)
+        #[structopt(long, default_value = "js")]
+        target: Target,
+
+        /// Verify every remote module resolved against the lockfile, erroring on anything
+        /// unrecognized or changed (only applies to --target js, the only target that resolves
+        /// remote modules)
+        #[structopt(long, conflicts_with = "lock-write")]
+        lock: bool,
+
+        /// Like --lock, but also records a hash for any module seen for the first time instead of
+        /// erroring on it
+        #[structopt(long)]
+        lock_write: bool,
+
+        /// Lockfile to verify against (with --lock) or write to (with --lock-write)
+        #[structopt(long, default_value = "quench.lock")]
+        lockfile: PathBuf,
+    },
+}
+
+/// Type-checks `file`, returning the typed IR a `Backend` can compile. Reports and exits on any
+/// diagnostic or type error, the same way `check` reports them for display rather than compilation.
+fn type_check(file: &PathBuf) -> anyhow::Result<(Url, String, types::File)> {
+    let uri = Url::from_file_path(file.canonicalize()?).unwrap();
+    let source = slurp::read_all_to_string(file)?;
+
+    let mut db = db::Database::default();
+    db.open_document(uri.clone(), source.clone())?;
+
+    let diagnostics = db.diagnostics(uri.clone());
+    if !diagnostics.is_empty() {
+        for diagnostic in diagnostics {
+            println!("{}", diagnostic.message);
+        }
+        anyhow::bail!("{} has diagnostics, not compiling", file.display());
+    }
+
+    let ast = db.file(uri.clone()).ok_or_else(|| anyhow::anyhow!("{} failed to parse", file.display()))?;
+    let typed = types::infer(&ast).map_err(|errors| {
+        anyhow::anyhow!(errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))
+    })?;
+
+    Ok((uri, source, typed))
+}
+
+fn compile(file: PathBuf, target: Target, out: Option<PathBuf>) -> anyhow::Result<()> {
+    let (_uri, source, typed) = type_check(&file)?;
+    let out = out.unwrap_or_else(|| file.with_extension(target.extension()));
+
+    if !target.backend().compile_to_file(&typed, &file, &source, &out)? {
+        anyhow::bail!("{} uses a construct the {:?} target doesn't support yet", file.display(), target);
+    }
+    println!("wrote {}", out.display());
+    Ok(())
+}
+
+async fn run(
+    file: PathBuf,
+    target: Target,
+    lock: bool,
+    lock_write: bool,
+    lockfile: PathBuf,
+) -> anyhow::Result<()> {
+    let (uri, source, typed) = type_check(&file)?;
+
+    match target {
+        Target::Js => {
+            let program = compiler::compile_file(&typed)?;
+            let (js, source_map) = codegen::generate(&program, &file.display().to_string(), &source);
+
+            // `run` has no `.js` artifact on disk the way `compile` does, but the source map is
+            // still worth writing next to the original file and referencing from the generated
+            // code, so Deno's stack traces point back at quench source instead of the emitted JS
+            let map_path = file.with_extension("js.map");
+            std::fs::write(&map_path, serde_json::to_string(&source_map)?)?;
+            let js = format!(
+                "{}\n//# sourceMappingURL={}\n",
+                js,
+                map_path.file_name().unwrap().to_string_lossy()
+            );
+
+            let mut loader = FixedLoader::new(uri, js);
+
+            let locked = if lock || lock_write {
+                let lockfile = Arc::new(Mutex::new(Lockfile::load(lockfile)));
+                loader.lock = Some(LockConfig {
+                    mode: if lock_write { LockMode::Write } else { LockMode::Verify },
+                    lockfile: lockfile.clone(),
+                });
+                Some(lockfile)
+            } else {
+                None
+            };
+
+            runtime::run_file(loader).await?;
+
+            if let Some(lockfile) = locked {
+                lockfile.lock().unwrap().write()?;
+            }
+        }
+        Target::Native => {
+            let context = Context::create();
+            let module = llvm::compile_file(&context, &typed)
+                .ok_or_else(|| anyhow::anyhow!("{} uses a construct the native target doesn't support yet", file.display()))?;
+            let code = llvm::jit_run(module)?;
+            if code != 0 {
+                std::process::exit(code);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn fmt(file: PathBuf, check: bool) -> anyhow::Result<()> {
+    let uri = Url::from_file_path(file.canonicalize()?).unwrap();
+    let original = slurp::read_all_to_string(&file)?;
 
-    let uri = Url::from_file_path(opt.file.canonicalize()?).unwrap();
     let mut db = db::Database::default();
-    db.open_document(uri.clone(), slurp::read_all_to_string(opt.file)?)?;
+    db.open_document(uri.clone(), original.clone())?;
+    let formatted = db
+        .formatted(uri)
+        .ok_or_else(|| anyhow::anyhow!("{} couldn't be formatted", file.display()))?;
+
+    if check {
+        if *formatted == original {
+            Ok(())
+        } else {
+            println!("{} is not formatted", file.display());
+            std::process::exit(1);
+        }
+    } else {
+        std::fs::write(file, &*formatted)?;
+        Ok(())
+    }
+}
+
+fn check(file: PathBuf) -> anyhow::Result<()> {
+    let uri = Url::from_file_path(file.canonicalize()?).unwrap();
+    let mut db = db::Database::default();
+    db.open_document(uri.clone(), slurp::read_all_to_string(file)?)?;
 
     let ast = db.ast(uri.clone()).unwrap();
     println!("AST:");
@@ -80,3 +272,29 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+
+    match opt.cmd {
+        Some(Command::Test { filter, root }) => {
+            let passed = quench::test_runner::run(&root, filter.as_deref()).await?;
+            if !passed {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Fmt { file, check }) => fmt(file, check)?,
+        Some(Command::Compile { file, target, out }) => compile(file, target, out)?,
+        Some(Command::Run {
+            file,
+            target,
+            lock,
+            lock_write,
+            lockfile,
+        }) => run(file, target, lock, lock_write, lockfile).await?,
+        None => check(opt.file.ok_or_else(|| anyhow::anyhow!("expected a source file"))?)?,
+    }
+
+    Ok(())
+}
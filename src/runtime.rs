@@ -0,0 +1,90 @@
+// runs compiled Quench output in its own `deno_core::JsRuntime`, used by both `quench run --target
+// js` (`run_file`) and `test_runner::run_file` (`run_tests`, which additionally bootstraps the
+// `test(...)` global).
+
+use crate::loader::FixedLoader;
+use deno_core::{error::AnyError, Extension, JsRuntime, OpState, RuntimeOptions};
+use std::{cell::RefCell, rc::Rc};
+
+/// The outcome of one `test(name, fn)` case reported by a running script; `passed` is false if
+/// `fn` threw, in which case `message` holds the stringified exception.
+#[derive(Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    pub message: Option<String>,
+}
+
+// defines the `test` global that compiled JS for a Quench `test(name, fn)` call resolves to (see
+// `compiler::compile_identifier`): it runs `fn` and reports the outcome back to Rust via the
+// `op_test_result` op, since `FixedLoader` never hands the embedder a module's return value to
+// inspect directly
+const TEST_BOOTSTRAP: &str = r#"
+function test(name, fn) {
+  try {
+    fn();
+    Deno.core.opSync("op_test_result", name, true, null);
+  } catch (e) {
+    Deno.core.opSync("op_test_result", name, false, String(e));
+  }
+}
+"#;
+
+fn test_extension() -> Extension {
+    Extension::builder()
+        .ops(vec![deno_core::op_sync(
+            "op_test_result",
+            |state: &mut OpState, (name, passed, message): (String, bool, Option<String>), _: ()| {
+                state
+                    .borrow::<Rc<RefCell<Vec<TestResult>>>>()
+                    .borrow_mut()
+                    .push(TestResult {
+                        name,
+                        passed,
+                        message,
+                    });
+                Ok::<_, AnyError>(())
+            },
+        )])
+        .build()
+}
+
+/// Runs `loader.main_module`, reporting every `test(...)` case it declares, in the order they ran.
+pub async fn run_tests(loader: FixedLoader) -> Result<Vec<TestResult>, AnyError> {
+    let main_module = loader.main_module.clone();
+    let results = Rc::new(RefCell::new(vec![]));
+
+    let mut runtime = JsRuntime::new(RuntimeOptions {
+        module_loader: Some(Rc::new(loader)),
+        extensions: vec![test_extension()],
+        ..Default::default()
+    });
+    runtime.op_state().borrow_mut().put(results.clone());
+    runtime.execute_script("quench:test_bootstrap", TEST_BOOTSTRAP)?;
+
+    let module_id = runtime.load_main_module(&main_module, None).await?;
+    let evaluated = runtime.mod_evaluate(module_id);
+    runtime.run_event_loop(false).await?;
+    evaluated.await??;
+
+    Ok(Rc::try_unwrap(results)
+        .expect("no other references to `results` outlive the runtime")
+        .into_inner())
+}
+
+/// Runs `loader.main_module` to completion, with no `test(...)` bootstrap and nothing collected
+/// back out of it -- the `quench run --target js` counterpart of `run_tests`, for a script that's
+/// just meant to run, not report pass/fail cases.
+pub async fn run_file(loader: FixedLoader) -> Result<(), AnyError> {
+    let main_module = loader.main_module.clone();
+
+    let mut runtime = JsRuntime::new(RuntimeOptions {
+        module_loader: Some(Rc::new(loader)),
+        ..Default::default()
+    });
+
+    let module_id = runtime.load_main_module(&main_module, None).await?;
+    let evaluated = runtime.mod_evaluate(module_id);
+    runtime.run_event_loop(false).await?;
+    evaluated.await?
+}
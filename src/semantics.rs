@@ -0,0 +1,345 @@
+// a semantic-analysis pass over `syntax::File`, run before codegen, that catches references to
+// names the language doesn't know about: analogous to a typed element checker reporting "pushing
+// invalid type" or "index out of range" errors with exact source locations, except here the
+// "elements" are the handful of builtins `compiler::compile_identifier` currently recognizes
+
+use crate::syntax::{self, Span};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Builtin {
+    /// a callable builtin and the exact number of arguments it accepts; the language has no
+    /// variadic or optional-argument support yet, so arity is always exact
+    Function { arity: usize },
+    /// a builtin that can be referenced but not called, like a global constant
+    Value,
+}
+
+fn lookup(name: &str) -> Option<Builtin> {
+    match name {
+        "print" => Some(Builtin::Function { arity: 1 }),
+        "args" => Some(Builtin::Value),
+        "test" => Some(Builtin::Function { arity: 2 }),
+        _ => None,
+    }
+}
+
+/// Names declared by `let` and `function` so far, in the same `Builtin` terms as the fixed
+/// `lookup` table above. The language has no block scoping yet, so a fresh clone of this stands in
+/// for each nested body -- bindings a block introduces don't escape it, but it never needs to
+/// notice a shadowed outer binding coming back into view either.
+type Locals = HashMap<String, Builtin>;
+
+fn resolve(name: &str, locals: &Locals) -> Option<Builtin> {
+    locals.get(name).copied().or_else(|| lookup(name))
+}
+
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum SemanticError {
+    #[error("unresolved identifier `{name}`")]
+    UnresolvedIdentifier { name: String, span: Span },
+
+    #[error("call to unknown function `{name}`")]
+    UnknownFunction { name: String, span: Span },
+
+    #[error("`{name}` is not a function and can't be called")]
+    NotCallable { name: String, span: Span },
+
+    #[error("`{name}` expects {expected} argument(s), but {found} were given")]
+    Arity {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+}
+
+impl SemanticError {
+    pub fn span(&self) -> Span {
+        match self {
+            SemanticError::UnresolvedIdentifier { span, .. }
+            | SemanticError::UnknownFunction { span, .. }
+            | SemanticError::NotCallable { span, .. }
+            | SemanticError::Arity { span, .. } => *span,
+        }
+    }
+}
+
+fn check_expression(expr: &syntax::Expression, locals: &Locals, errors: &mut Vec<SemanticError>) {
+    match expr {
+        syntax::Expression::Lit(_) => {}
+        syntax::Expression::Id(id) => {
+            if resolve(&id.name, locals).is_none() {
+                errors.push(SemanticError::UnresolvedIdentifier {
+                    name: id.name.clone(),
+                    span: id.span,
+                });
+            }
+        }
+        syntax::Expression::Call(syntax::Call {
+            function,
+            arguments,
+            span,
+        }) => {
+            match resolve(&function.name, locals) {
+                None => errors.push(SemanticError::UnknownFunction {
+                    name: function.name.clone(),
+                    span: *span,
+                }),
+                Some(Builtin::Value) => errors.push(SemanticError::NotCallable {
+                    name: function.name.clone(),
+                    span: *span,
+                }),
+                Some(Builtin::Function { arity }) if arity != arguments.len() => {
+                    errors.push(SemanticError::Arity {
+                        name: function.name.clone(),
+                        expected: arity,
+                        found: arguments.len(),
+                        span: *span,
+                    });
+                }
+                Some(Builtin::Function { .. }) => {}
+            }
+            for argument in arguments {
+                check_expression(argument, locals, errors);
+            }
+        }
+        syntax::Expression::Binary(left, _, right, _) | syntax::Expression::Logical(left, _, right, _) => {
+            check_expression(left, locals, errors);
+            check_expression(right, locals, errors);
+        }
+        syntax::Expression::Assign(name, _, value, _) => {
+            if resolve(&name.name, locals).is_none() {
+                errors.push(SemanticError::UnresolvedIdentifier {
+                    name: name.name.clone(),
+                    span: name.span,
+                });
+            }
+            check_expression(value, locals, errors);
+        }
+    }
+}
+
+fn check_statement(stmt: &syntax::Statement, locals: &mut Locals, errors: &mut Vec<SemanticError>) {
+    match stmt {
+        syntax::Statement::Expr(expr) => check_expression(expr, locals, errors),
+        syntax::Statement::Let { name, init, .. } => {
+            if let Some(init) = init {
+                check_expression(init, locals, errors);
+            }
+            locals.insert(name.name.clone(), Builtin::Value);
+        }
+        syntax::Statement::If {
+            test,
+            consequent,
+            alternate,
+            ..
+        } => {
+            check_expression(test, locals, errors);
+            check_block(consequent, locals, errors);
+            if let Some(alternate) = alternate {
+                check_block(alternate, locals, errors);
+            }
+        }
+        syntax::Statement::While { test, body, .. } => {
+            check_expression(test, locals, errors);
+            check_block(body, locals, errors);
+        }
+        syntax::Statement::Return { value, .. } => {
+            if let Some(value) = value {
+                check_expression(value, locals, errors);
+            }
+        }
+        syntax::Statement::FunctionDecl {
+            name, params, body, ..
+        } => {
+            locals.insert(
+                name.name.clone(),
+                Builtin::Function {
+                    arity: params.len(),
+                },
+            );
+            let mut inner = locals.clone();
+            for param in params {
+                inner.insert(param.name.clone(), Builtin::Value);
+            }
+            for statement in body {
+                check_statement(statement, &mut inner, errors);
+            }
+        }
+    }
+}
+
+/// Checks a nested block (an `if`/`while` body) against a scope of its own, so a `let` inside it
+/// doesn't leak into whatever follows the block in the enclosing one.
+fn check_block(body: &[syntax::Statement], locals: &Locals, errors: &mut Vec<SemanticError>) {
+    let mut inner = locals.clone();
+    for statement in body {
+        check_statement(statement, &mut inner, errors);
+    }
+}
+
+/// Walks `file` looking for unresolved identifiers, calls to unknown or non-callable names, and
+/// arity mismatches against the handful of builtins the compiler recognizes, plus anything `let`
+/// and `function` declarations have added to scope by the time each statement runs. Returns every
+/// error found, in source order, rather than stopping at the first one.
+pub fn check(file: &syntax::File) -> Vec<SemanticError> {
+    let mut errors = vec![];
+    let mut locals = Locals::new();
+    for statement in &file.body {
+        check_statement(statement, &mut locals, &mut errors);
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span() -> Span {
+        Span {
+            start: tree_sitter::Point { row: 0, column: 0 },
+            end: tree_sitter::Point { row: 0, column: 1 },
+        }
+    }
+
+    fn id(name: &str) -> syntax::Identifier {
+        syntax::Identifier {
+            name: String::from(name),
+            span: span(),
+        }
+    }
+
+    fn call(function: syntax::Identifier, arguments: Vec<syntax::Expression>) -> syntax::Call {
+        syntax::Call {
+            function,
+            arguments,
+            span: span(),
+        }
+    }
+
+    fn lit(value: &str) -> syntax::Expression {
+        syntax::Expression::Lit(syntax::Literal::Str(String::from(value), span()))
+    }
+
+    fn file(statements: Vec<syntax::Statement>) -> syntax::File {
+        syntax::File { body: statements }
+    }
+
+    #[test]
+    fn test_unresolved_identifier() {
+        let f = file(vec![syntax::Statement::Expr(syntax::Expression::Id(id(
+            "nonexistent",
+        )))]);
+        assert_eq!(
+            check(&f),
+            vec![SemanticError::UnresolvedIdentifier {
+                name: String::from("nonexistent"),
+                span: id("nonexistent").span,
+            }],
+        );
+    }
+
+    #[test]
+    fn test_call_to_unknown_function() {
+        let f = file(vec![syntax::Statement::Expr(syntax::Expression::Call(
+            call(id("nonexistent"), vec![]),
+        ))]);
+        assert_eq!(
+            check(&f),
+            vec![SemanticError::UnknownFunction {
+                name: String::from("nonexistent"),
+                span: call(id("nonexistent"), vec![]).span,
+            }],
+        );
+    }
+
+    #[test]
+    fn test_call_to_non_function_value() {
+        let f = file(vec![syntax::Statement::Expr(syntax::Expression::Call(
+            call(id("args"), vec![]),
+        ))]);
+        assert_eq!(
+            check(&f),
+            vec![SemanticError::NotCallable {
+                name: String::from("args"),
+                span: call(id("args"), vec![]).span,
+            }],
+        );
+    }
+
+    #[test]
+    fn test_arity_mismatch() {
+        let f = file(vec![syntax::Statement::Expr(syntax::Expression::Call(
+            call(id("print"), vec![lit("a"), lit("b")]),
+        ))]);
+        assert_eq!(
+            check(&f),
+            vec![SemanticError::Arity {
+                name: String::from("print"),
+                expected: 1,
+                found: 2,
+                span: call(id("print"), vec![]).span,
+            }],
+        );
+    }
+
+    #[test]
+    fn test_valid_call_has_no_errors() {
+        let f = file(vec![syntax::Statement::Expr(syntax::Expression::Call(
+            call(id("print"), vec![lit("hello")]),
+        ))]);
+        assert_eq!(check(&f), vec![]);
+    }
+
+    #[test]
+    fn test_let_binding_resolves_in_later_statements() {
+        let f = file(vec![
+            syntax::Statement::Let {
+                name: id("x"),
+                init: Some(lit("hi")),
+                span: span(),
+            },
+            syntax::Statement::Expr(syntax::Expression::Id(id("x"))),
+        ]);
+        assert_eq!(check(&f), vec![]);
+    }
+
+    #[test]
+    fn test_let_binding_does_not_leak_out_of_its_block() {
+        let f = file(vec![
+            syntax::Statement::If {
+                test: lit("cond"),
+                consequent: vec![syntax::Statement::Let {
+                    name: id("x"),
+                    init: None,
+                    span: span(),
+                }],
+                alternate: None,
+                span: span(),
+            },
+            syntax::Statement::Expr(syntax::Expression::Id(id("x"))),
+        ]);
+        assert_eq!(
+            check(&f),
+            vec![SemanticError::UnresolvedIdentifier {
+                name: String::from("x"),
+                span: id("x").span,
+            }],
+        );
+    }
+
+    #[test]
+    fn test_function_params_resolve_inside_its_body() {
+        let f = file(vec![syntax::Statement::FunctionDecl {
+            name: id("greet"),
+            params: vec![id("name")],
+            body: vec![syntax::Statement::Expr(syntax::Expression::Call(call(
+                id("print"),
+                vec![syntax::Expression::Id(id("name"))],
+            )))],
+            span: span(),
+        }]);
+        assert_eq!(check(&f), vec![]);
+    }
+}
@@ -0,0 +1,97 @@
+// encodes a Source Map v3 object (https://sourcemaps.info/spec.html) pairing generated JS
+// positions with the original quench `syntax::Span`s recorded on each `estree` node's `loc`,
+// built up by `codegen::generate` as it emits text.
+
+use serde::Serialize;
+
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SourceMap {
+    pub version: u8,
+    pub sources: Vec<String>,
+    #[serde(rename = "sourcesContent")]
+    pub sources_content: Vec<String>,
+    pub names: Vec<String>,
+    pub mappings: String,
+}
+
+/// Builds the base64-VLQ `mappings` field one segment at a time, in generated-position order.
+/// `generatedColumn` resets to 0 at the start of each generated line, per spec; `sourceIndex`,
+/// `originalLine`, and `originalColumn` are delta-encoded continuously across the whole mapping,
+/// line breaks notwithstanding. Only ever tracks a single source file, so the `sourceIndex` field
+/// is always a zero delta.
+#[derive(Default)]
+pub struct MappingsBuilder {
+    mappings: String,
+    generated_line: usize,
+    generated_column: usize,
+    original_line: i64,
+    original_column: i64,
+    segment_on_line: bool,
+}
+
+impl MappingsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `(generated_line, generated_column)` (both 0-based) in the emitted JS
+    /// corresponds to `(original_line, original_column)` (both 0-based) in the tracked source.
+    pub fn add(
+        &mut self,
+        generated_line: usize,
+        generated_column: usize,
+        original_line: usize,
+        original_column: usize,
+    ) {
+        while self.generated_line < generated_line {
+            self.mappings.push(';');
+            self.generated_line += 1;
+            self.generated_column = 0;
+            self.segment_on_line = false;
+        }
+
+        if self.segment_on_line {
+            self.mappings.push(',');
+        }
+        self.segment_on_line = true;
+
+        encode_vlq(generated_column as i64 - self.generated_column as i64, &mut self.mappings);
+        encode_vlq(0, &mut self.mappings);
+        encode_vlq(original_line as i64 - self.original_line, &mut self.mappings);
+        encode_vlq(original_column as i64 - self.original_column, &mut self.mappings);
+
+        self.generated_column = generated_column;
+        self.original_line = original_line as i64;
+        self.original_column = original_column as i64;
+    }
+
+    /// `source_path` is the original quench file's path (what `sources` is supposed to hold, per
+    /// the Source Map v3 spec); `source_content` is its actual text, embedded in `sourcesContent`
+    /// so a debugger can show the original source even without the file on disk at that path.
+    pub fn finish(self, source_path: String, source_content: String) -> SourceMap {
+        SourceMap {
+            version: 3,
+            sources: vec![source_path],
+            sources_content: vec![source_content],
+            names: vec![],
+            mappings: self.mappings,
+        }
+    }
+}
+
+fn encode_vlq(value: i64, out: &mut String) {
+    let mut vlq = if value < 0 { (-value << 1) | 1 } else { value << 1 };
+    loop {
+        let mut digit = vlq & 0x1f;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0x20;
+        }
+        out.push(BASE64_CHARS[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
@@ -1,3 +1,21 @@
+// a source range, in the same tree_sitter::Point terms as the CST itself, so it can be handed
+// straight to `text::Index::to_lsp` wherever an error needs to become an LSP `Range`; used to
+// locate semantic-analysis errors (see the `semantics` module)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Span {
+    pub start: tree_sitter::Point,
+    pub end: tree_sitter::Point,
+}
+
+impl Span {
+    fn of(node: &tree_sitter::Node) -> Self {
+        Span {
+            start: node.start_position(),
+            end: node.end_position(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct File {
     pub body: Vec<Statement>,
@@ -6,6 +24,80 @@ pub struct File {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Statement {
     Expr(Expression),
+
+    Let {
+        name: Identifier,
+        init: Option<Expression>,
+        span: Span,
+    },
+
+    If {
+        test: Expression,
+        consequent: Vec<Statement>,
+        alternate: Option<Vec<Statement>>,
+        span: Span,
+    },
+
+    While {
+        test: Expression,
+        body: Vec<Statement>,
+        span: Span,
+    },
+
+    Return {
+        value: Option<Expression>,
+        span: Span,
+    },
+
+    FunctionDecl {
+        name: Identifier,
+        params: Vec<Identifier>,
+        body: Vec<Statement>,
+        span: Span,
+    },
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Expr(expr) => expr.span(),
+            Statement::Let { span, .. }
+            | Statement::If { span, .. }
+            | Statement::While { span, .. }
+            | Statement::Return { span, .. }
+            | Statement::FunctionDecl { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulus,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LogicalOp {
+    And,
+    Or,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AssignOp {
+    Equal,
+    AddEqual,
+    SubtractEqual,
+    MultiplyEqual,
+    DivideEqual,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -13,22 +105,64 @@ pub enum Expression {
     Lit(Literal),
     Id(Identifier),
     Call(Call),
+    Binary(Box<Expression>, BinaryOp, Box<Expression>, Span),
+    Logical(Box<Expression>, LogicalOp, Box<Expression>, Span),
+    Assign(Identifier, AssignOp, Box<Expression>, Span),
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+impl Expression {
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Lit(lit) => lit.span(),
+            Expression::Id(id) => id.span,
+            Expression::Call(call) => call.span,
+            Expression::Binary(_, _, _, span)
+            | Expression::Logical(_, _, _, span)
+            | Expression::Assign(_, _, _, span) => *span,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum Literal {
-    Str(String),
+    Str(String, Span),
+    Num(f64, Span),
+    Bool(bool, Span),
 }
 
+impl Literal {
+    pub fn span(&self) -> Span {
+        match self {
+            Literal::Str(_, span) | Literal::Num(_, span) | Literal::Bool(_, span) => *span,
+        }
+    }
+}
+
+impl PartialEq for Literal {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Literal::Str(a, sa), Literal::Str(b, sb)) => a == b && sa == sb,
+            // see `estree::Value`'s impl for why this is fine for our purposes
+            (Literal::Num(a, sa), Literal::Num(b, sb)) => a.to_ne_bytes() == b.to_ne_bytes() && sa == sb,
+            (Literal::Bool(a, sa), Literal::Bool(b, sb)) => a == b && sa == sb,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Literal {}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Identifier {
     pub name: String,
+    pub span: Span,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Call {
     pub function: Identifier,
     pub arguments: Vec<Expression>,
+    pub span: Span,
 }
 
 pub trait Node {
@@ -39,28 +173,150 @@ pub trait Node {
 
 impl Node for File {
     fn make(text: &str, node: &tree_sitter::Node) -> Option<Self> {
-        let mut cursor = node.walk();
         Some(File {
-            body: node
-                .children(&mut cursor)
-                .filter_map(|child| Statement::make(text, &child))
-                .collect(),
+            body: block_statements(text, node),
         })
     }
 }
 
+/// Collects every statement among `node`'s children, in source order, dropping any child the
+/// grammar didn't recognize or that failed to convert. Shared by `File::make` (whose `node` is the
+/// whole source file) and every construct below with a `{ ... }` block body.
+fn block_statements(text: &str, node: &tree_sitter::Node) -> Vec<Statement> {
+    let mut cursor = node.walk();
+    node.children(&mut cursor)
+        .filter_map(|child| Statement::make(text, &child))
+        .collect()
+}
+
 impl Node for Statement {
     fn make(text: &str, node: &tree_sitter::Node) -> Option<Self> {
-        Expression::make(text, node).map(Statement::Expr)
+        match node.kind() {
+            "let_declaration" => Some(Statement::Let {
+                name: Identifier::make(text, &node.child_by_field_name("name")?)?,
+                init: node
+                    .child_by_field_name("value")
+                    .and_then(|child| Expression::make(text, &child)),
+                span: Span::of(node),
+            }),
+            "if_statement" => Some(Statement::If {
+                test: Expression::make(text, &node.child_by_field_name("condition")?)?,
+                consequent: block_statements(text, &node.child_by_field_name("consequence")?),
+                alternate: node
+                    .child_by_field_name("alternative")
+                    .map(|child| block_statements(text, &child)),
+                span: Span::of(node),
+            }),
+            "while_statement" => Some(Statement::While {
+                test: Expression::make(text, &node.child_by_field_name("condition")?)?,
+                body: block_statements(text, &node.child_by_field_name("body")?),
+                span: Span::of(node),
+            }),
+            "return_statement" => Some(Statement::Return {
+                value: node
+                    .child_by_field_name("value")
+                    .and_then(|child| Expression::make(text, &child)),
+                span: Span::of(node),
+            }),
+            "function_declaration" => Some(Statement::FunctionDecl {
+                name: Identifier::make(text, &node.child_by_field_name("name")?)?,
+                params: {
+                    let params_child = node.child_by_field_name("parameters")?;
+                    let mut cursor = params_child.walk();
+                    params_child
+                        .children(&mut cursor)
+                        .filter_map(|child| Identifier::make(text, &child))
+                        .collect()
+                },
+                body: block_statements(text, &node.child_by_field_name("body")?),
+                span: Span::of(node),
+            }),
+            _ => Expression::make(text, node).map(Statement::Expr),
+        }
     }
 }
 
 impl Node for Expression {
     fn make(text: &str, node: &tree_sitter::Node) -> Option<Self> {
         match node.kind() {
-            "string" => Literal::make(text, node).map(Expression::Lit),
+            "string" | "number" | "true" | "false" => Literal::make(text, node).map(Expression::Lit),
             "identifier" => Identifier::make(text, node).map(Expression::Id),
             "call" => Call::make(text, node).map(Expression::Call),
+            "binary_expression" => {
+                let left = Box::new(Expression::make(text, &node.child_by_field_name("left")?)?);
+                let right = Box::new(Expression::make(text, &node.child_by_field_name("right")?)?);
+                let operator = node.child_by_field_name("operator")?;
+                Some(Expression::Binary(
+                    left,
+                    BinaryOp::make(operator.kind())?,
+                    right,
+                    Span::of(node),
+                ))
+            }
+            "logical_expression" => {
+                let left = Box::new(Expression::make(text, &node.child_by_field_name("left")?)?);
+                let right = Box::new(Expression::make(text, &node.child_by_field_name("right")?)?);
+                let operator = node.child_by_field_name("operator")?;
+                Some(Expression::Logical(
+                    left,
+                    LogicalOp::make(operator.kind())?,
+                    right,
+                    Span::of(node),
+                ))
+            }
+            "assignment_expression" => {
+                let name = Identifier::make(text, &node.child_by_field_name("left")?)?;
+                let value = Box::new(Expression::make(text, &node.child_by_field_name("right")?)?);
+                let operator = node.child_by_field_name("operator")?;
+                Some(Expression::Assign(
+                    name,
+                    AssignOp::make(operator.kind())?,
+                    value,
+                    Span::of(node),
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl BinaryOp {
+    fn make(kind: &str) -> Option<Self> {
+        match kind {
+            "+" => Some(BinaryOp::Add),
+            "-" => Some(BinaryOp::Subtract),
+            "*" => Some(BinaryOp::Multiply),
+            "/" => Some(BinaryOp::Divide),
+            "%" => Some(BinaryOp::Modulus),
+            "==" => Some(BinaryOp::Equal),
+            "!=" => Some(BinaryOp::NotEqual),
+            "<" => Some(BinaryOp::Less),
+            "<=" => Some(BinaryOp::LessEqual),
+            ">" => Some(BinaryOp::Greater),
+            ">=" => Some(BinaryOp::GreaterEqual),
+            _ => None,
+        }
+    }
+}
+
+impl LogicalOp {
+    fn make(kind: &str) -> Option<Self> {
+        match kind {
+            "&&" => Some(LogicalOp::And),
+            "||" => Some(LogicalOp::Or),
+            _ => None,
+        }
+    }
+}
+
+impl AssignOp {
+    fn make(kind: &str) -> Option<Self> {
+        match kind {
+            "=" => Some(AssignOp::Equal),
+            "+=" => Some(AssignOp::AddEqual),
+            "-=" => Some(AssignOp::SubtractEqual),
+            "*=" => Some(AssignOp::MultiplyEqual),
+            "/=" => Some(AssignOp::DivideEqual),
             _ => None,
         }
     }
@@ -68,12 +324,23 @@ impl Node for Expression {
 
 impl Node for Literal {
     fn make(text: &str, node: &tree_sitter::Node) -> Option<Self> {
-        let value = node
-            .utf8_text(text.as_bytes())
-            .ok()?
-            .strip_prefix("\"")?
-            .strip_suffix("\"")?;
-        Some(Literal::Str(String::from(value)))
+        match node.kind() {
+            "string" => {
+                let value = node
+                    .utf8_text(text.as_bytes())
+                    .ok()?
+                    .strip_prefix('"')?
+                    .strip_suffix('"')?;
+                Some(Literal::Str(String::from(value), Span::of(node)))
+            }
+            "number" => {
+                let value = node.utf8_text(text.as_bytes()).ok()?.parse().ok()?;
+                Some(Literal::Num(value, Span::of(node)))
+            }
+            "true" => Some(Literal::Bool(true, Span::of(node))),
+            "false" => Some(Literal::Bool(false, Span::of(node))),
+            _ => None,
+        }
     }
 }
 
@@ -81,6 +348,7 @@ impl Node for Identifier {
     fn make(text: &str, node: &tree_sitter::Node) -> Option<Self> {
         Some(Identifier {
             name: String::from(node.utf8_text(text.as_bytes()).ok()?),
+            span: Span::of(node),
         })
     }
 }
@@ -97,6 +365,7 @@ impl Node for Call {
                     .filter_map(|child| Expression::make(text, &child))
                     .collect()
             },
+            span: Span::of(node),
         })
     }
 }
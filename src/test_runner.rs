@@ -0,0 +1,179 @@
+// discovers `*_test.qn` files, compiles and runs each in its own `runtime::run_tests` sandbox, and
+// reports a pass/fail summary -- the `quench test` counterpart of `deno test`.
+
+use crate::{
+    codegen, compiler,
+    db::{self, QueryGroup},
+    loader::FixedLoader,
+    runtime, types,
+};
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use url::Url;
+use walkdir::WalkDir;
+
+/// One reported outcome: either a `test(...)` case the file declared, or -- if the file didn't
+/// even compile -- a single synthetic failure standing in for the whole file.
+struct CaseResult {
+    file: PathBuf,
+    name: String,
+    passed: bool,
+    message: Option<String>,
+    elapsed: Duration,
+}
+
+fn is_test_file(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new("qn"))
+        && path
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .map_or(false, |stem| stem.ends_with("_test"))
+}
+
+/// Recursively finds every `*_test.qn` file under `root`, in a stable (sorted) order.
+fn discover(root: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<_> = WalkDir::new(root)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .map(walkdir::DirEntry::into_path)
+        .filter(|path| is_test_file(path))
+        .collect();
+    paths.sort();
+    paths
+}
+
+async fn run_file(path: &Path) -> anyhow::Result<Vec<CaseResult>> {
+    let uri = Url::from_file_path(path.canonicalize()?).unwrap();
+    let source = slurp::read_all_to_string(path)?;
+
+    let mut db = db::Database::default();
+    db.open_document(uri.clone(), source.clone())?;
+
+    let diagnostics = db.diagnostics(uri.clone());
+    if !diagnostics.is_empty() {
+        return Ok(vec![CaseResult {
+            file: path.to_path_buf(),
+            name: String::from("(compile)"),
+            passed: false,
+            message: Some(
+                diagnostics
+                    .iter()
+                    .map(|diagnostic| diagnostic.message.clone())
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            ),
+            elapsed: Duration::default(),
+        }]);
+    }
+
+    // `diagnostics` being empty means the file parsed, resolved its names, and type-checked, but
+    // `db.file` can still come back empty and the compiler can still fail to lower a construct, so
+    // each of these steps still reports a failure rather than unwrap
+    let file = db.file(uri.clone());
+    let typed = match file.as_deref().map(types::infer) {
+        Some(Ok(typed)) => typed,
+        Some(Err(errors)) => {
+            return Ok(vec![CaseResult {
+                file: path.to_path_buf(),
+                name: String::from("(compile)"),
+                passed: false,
+                message: Some(
+                    errors
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                ),
+                elapsed: Duration::default(),
+            }])
+        }
+        None => {
+            return Ok(vec![CaseResult {
+                file: path.to_path_buf(),
+                name: String::from("(compile)"),
+                passed: false,
+                message: Some(String::from("this file has no compiler support yet")),
+                elapsed: Duration::default(),
+            }])
+        }
+    };
+
+    let program = match compiler::compile_file(&typed) {
+        Ok(program) => program,
+        Err(error) => {
+            return Ok(vec![CaseResult {
+                file: path.to_path_buf(),
+                name: String::from("(compile)"),
+                passed: false,
+                message: Some(error.to_string()),
+                elapsed: Duration::default(),
+            }])
+        }
+    };
+
+    // the source map `generate` also produces isn't useful here: `runtime::run_tests` runs
+    // straight off the in-memory module, with no `.js.map` sidecar for a browser or Deno stack
+    // trace to find on disk
+    let (js, _source_map) = codegen::generate(&program, &path.display().to_string(), &source);
+    let loader = FixedLoader::new(uri, js);
+
+    let start = Instant::now();
+    let results = runtime::run_tests(loader).await?;
+    let elapsed = start.elapsed();
+
+    Ok(results
+        .into_iter()
+        .map(|result| CaseResult {
+            file: path.to_path_buf(),
+            name: result.name,
+            passed: result.passed,
+            message: result.message,
+            elapsed,
+        })
+        .collect())
+}
+
+/// Discovers every `*_test.qn` file under `root`, runs them, prints a pass/fail line per case as
+/// it finishes plus a final summary, and returns whether every case passed, so the caller can pick
+/// the process exit code.
+pub async fn run(root: &Path, filter: Option<&str>) -> anyhow::Result<bool> {
+    let mut total = 0;
+    let mut failed = 0;
+
+    for path in discover(root) {
+        for case in run_file(&path).await? {
+            if matches!(filter, Some(filter) if !case.name.contains(filter)) {
+                continue;
+            }
+
+            total += 1;
+            if case.passed {
+                println!(
+                    "ok   {} > {} ({:?})",
+                    path.display(),
+                    case.name,
+                    case.elapsed
+                );
+            } else {
+                failed += 1;
+                println!(
+                    "FAIL {} > {} ({:?})",
+                    path.display(),
+                    case.name,
+                    case.elapsed
+                );
+                if let Some(message) = &case.message {
+                    println!("     {}", message);
+                }
+            }
+        }
+    }
+
+    println!();
+    println!("{} passed, {} failed, {} total", total - failed, failed, total);
+
+    Ok(failed == 0)
+}
@@ -0,0 +1,970 @@
+// a Hindley-Milner (Algorithm W) type inference pass, run after `semantics::check` finds no
+// errors, that turns a `syntax::File` into a typed IR in which every expression already knows its
+// own type; `compiler::compile_file` lowers that IR rather than the untyped `syntax` AST, so it
+// can assume well-typed input instead of re-deriving the same facts this pass already established.
+
+use crate::syntax::{self, Span};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TypeVarId(usize);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Type {
+    Var(TypeVarId),
+    String,
+    Number,
+    Boolean,
+    Null,
+    Fun(Box<Type>, Box<Type>),
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Type::Var(TypeVarId(id)) => write!(f, "t{}", id),
+            Type::String => write!(f, "String"),
+            Type::Number => write!(f, "Number"),
+            Type::Boolean => write!(f, "Boolean"),
+            Type::Null => write!(f, "Null"),
+            Type::Fun(arg, ret) => write!(f, "{} -> {}", arg, ret),
+        }
+    }
+}
+
+fn free_vars(ty: &Type) -> HashSet<TypeVarId> {
+    match ty {
+        Type::Var(v) => [*v].into_iter().collect(),
+        Type::String | Type::Number | Type::Boolean | Type::Null => HashSet::new(),
+        Type::Fun(arg, ret) => free_vars(arg).union(&free_vars(ret)).copied().collect(),
+    }
+}
+
+/// A type scheme: `ty`, generalized over the type variables in `vars` so each use of the scheme
+/// (see `Infer::instantiate`) gets its own fresh copy, per let-polymorphism.
+#[derive(Clone, Debug)]
+pub struct Scheme {
+    vars: Vec<TypeVarId>,
+    ty: Type,
+}
+
+fn free_vars_scheme(scheme: &Scheme) -> HashSet<TypeVarId> {
+    let mut vars = free_vars(&scheme.ty);
+    for var in &scheme.vars {
+        vars.remove(var);
+    }
+    vars
+}
+
+pub type Env = HashMap<String, Scheme>;
+
+/// Closes over every type variable in `ty` that isn't also free somewhere in `env`, producing the
+/// scheme a `let` or `function` binding records for it, so later uses each get their own fresh copy
+/// (e.g. `let id = ...; id("a"); id(1);` doesn't force `id`'s argument to a single type).
+pub fn generalize(env: &Env, ty: &Type) -> Scheme {
+    let env_free: HashSet<TypeVarId> = env.values().flat_map(free_vars_scheme).collect();
+    let vars = free_vars(ty).difference(&env_free).copied().collect();
+    Scheme { vars, ty: ty.clone() }
+}
+
+pub type Subst = HashMap<TypeVarId, Type>;
+
+fn apply(subst: &Subst, ty: &Type) -> Type {
+    match ty {
+        Type::Var(v) => subst.get(v).cloned().unwrap_or_else(|| ty.clone()),
+        Type::String | Type::Number | Type::Boolean | Type::Null => ty.clone(),
+        Type::Fun(arg, ret) => Type::Fun(Box::new(apply(subst, arg)), Box::new(apply(subst, ret))),
+    }
+}
+
+// composes `s1` after `s2`, i.e. the substitution that applying `s2` then `s1` would produce in
+// one pass; used to accumulate unification results across a call's arguments in sequence
+fn compose(s1: &Subst, s2: &Subst) -> Subst {
+    let mut result = s1.clone();
+    for (var, ty) in s2 {
+        result.insert(*var, apply(s1, ty));
+    }
+    result
+}
+
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum TypeError {
+    #[error("unbound name `{name}`")]
+    Unbound { name: String, span: Span },
+
+    #[error("expected type `{expected}`, found `{found}`")]
+    Mismatch {
+        expected: String,
+        found: String,
+        span: Span,
+    },
+
+    #[error("infinite type: `{var}` occurs in `{ty}`")]
+    InfiniteType { var: String, ty: String, span: Span },
+}
+
+impl TypeError {
+    pub fn span(&self) -> Span {
+        match self {
+            TypeError::Unbound { span, .. }
+            | TypeError::Mismatch { span, .. }
+            | TypeError::InfiniteType { span, .. } => *span,
+        }
+    }
+}
+
+fn bind(var: TypeVarId, ty: &Type, span: Span) -> Result<Subst, TypeError> {
+    if ty == &Type::Var(var) {
+        Ok(Subst::new())
+    } else if free_vars(ty).contains(&var) {
+        Err(TypeError::InfiniteType {
+            var: Type::Var(var).to_string(),
+            ty: ty.to_string(),
+            span,
+        })
+    } else {
+        Ok([(var, ty.clone())].into_iter().collect())
+    }
+}
+
+/// Unifies `a` and `b`, binding type variables (with an occurs-check) and recursing into function
+/// constructor arguments, erroring (tagged with `span`, for wherever the caller is unifying on
+/// behalf of) on any other mismatch.
+pub fn unify(a: &Type, b: &Type, span: Span) -> Result<Subst, TypeError> {
+    match (a, b) {
+        (Type::Var(v1), Type::Var(v2)) if v1 == v2 => Ok(Subst::new()),
+        (Type::Var(v), ty) | (ty, Type::Var(v)) => bind(*v, ty, span),
+        (Type::String, Type::String)
+        | (Type::Number, Type::Number)
+        | (Type::Boolean, Type::Boolean)
+        | (Type::Null, Type::Null) => Ok(Subst::new()),
+        (Type::Fun(a1, r1), Type::Fun(a2, r2)) => {
+            let s1 = unify(a1, a2, span)?;
+            let s2 = unify(&apply(&s1, r1), &apply(&s1, r2), span)?;
+            Ok(compose(&s2, &s1))
+        }
+        _ => Err(TypeError::Mismatch {
+            expected: a.to_string(),
+            found: b.to_string(),
+            span,
+        }),
+    }
+}
+
+/// Hands out fresh type variables, so every `instantiate`d use of a polymorphic builtin gets its
+/// own copies rather than accidentally sharing one with another use site.
+struct Infer {
+    next: usize,
+}
+
+impl Infer {
+    fn new() -> Self {
+        Infer { next: 0 }
+    }
+
+    fn fresh(&mut self) -> Type {
+        let var = TypeVarId(self.next);
+        self.next += 1;
+        Type::Var(var)
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        let subst: Subst = scheme.vars.iter().map(|&var| (var, self.fresh())).collect();
+        apply(&subst, &scheme.ty)
+    }
+}
+
+// the handful of builtins `semantics::lookup` already knows about, typed the same way
+// `compiler::compile_identifier` treats them: `print` takes a string and returns nothing, `args`
+// is left maximally polymorphic since the language has no array type yet to describe it
+// precisely, and `test` accepts a name and a callback of any single-argument shape (matching how
+// `chunk1-5`'s `test(name, fn)` builtin is actually used: passing an already-typed builtin like
+// `print` as `fn`).
+fn builtin_env() -> Env {
+    let mut env = Env::new();
+    env.insert(
+        String::from("print"),
+        Scheme {
+            vars: vec![],
+            ty: Type::Fun(Box::new(Type::String), Box::new(Type::Null)),
+        },
+    );
+    env.insert(
+        String::from("args"),
+        Scheme {
+            vars: vec![TypeVarId(0)],
+            ty: Type::Var(TypeVarId(0)),
+        },
+    );
+    env.insert(
+        String::from("test"),
+        Scheme {
+            vars: vec![TypeVarId(0), TypeVarId(1)],
+            ty: Type::Fun(
+                Box::new(Type::String),
+                Box::new(Type::Fun(
+                    Box::new(Type::Fun(
+                        Box::new(Type::Var(TypeVarId(0))),
+                        Box::new(Type::Var(TypeVarId(1))),
+                    )),
+                    Box::new(Type::Null),
+                )),
+            ),
+        },
+    );
+    env
+}
+
+#[derive(Clone, Debug)]
+pub struct File {
+    pub body: Vec<Statement>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Statement {
+    Expr(Expression),
+
+    Let {
+        name: String,
+        ty: Type,
+        init: Option<Expression>,
+        span: Span,
+    },
+
+    If {
+        test: Expression,
+        consequent: Vec<Statement>,
+        alternate: Option<Vec<Statement>>,
+        span: Span,
+    },
+
+    While {
+        test: Expression,
+        body: Vec<Statement>,
+        span: Span,
+    },
+
+    Return {
+        value: Option<Expression>,
+        span: Span,
+    },
+
+    FunctionDecl {
+        name: String,
+        params: Vec<(String, Type)>,
+        ret: Type,
+        body: Vec<Statement>,
+        span: Span,
+    },
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Expr(expr) => expr.span(),
+            Statement::Let { span, .. }
+            | Statement::If { span, .. }
+            | Statement::While { span, .. }
+            | Statement::Return { span, .. }
+            | Statement::FunctionDecl { span, .. } => *span,
+        }
+    }
+}
+
+/// A literal value, already classified by `infer_expression` -- unlike `syntax::Literal` this
+/// carries no span of its own, since the `Expression::Lit` variant that wraps it already has one.
+#[derive(Clone, Debug)]
+pub enum Literal {
+    Str(String),
+    Num(f64),
+    Bool(bool),
+}
+
+#[derive(Clone, Debug)]
+pub enum Expression {
+    Lit {
+        value: Literal,
+        ty: Type,
+        span: Span,
+    },
+    Id {
+        name: String,
+        ty: Type,
+        span: Span,
+    },
+    Call {
+        function: String,
+        function_span: Span,
+        arguments: Vec<Expression>,
+        ty: Type,
+        span: Span,
+    },
+    Binary {
+        op: syntax::BinaryOp,
+        left: Box<Expression>,
+        right: Box<Expression>,
+        ty: Type,
+        span: Span,
+    },
+    Logical {
+        op: syntax::LogicalOp,
+        left: Box<Expression>,
+        right: Box<Expression>,
+        ty: Type,
+        span: Span,
+    },
+    Assign {
+        name: String,
+        op: syntax::AssignOp,
+        value: Box<Expression>,
+        ty: Type,
+        span: Span,
+    },
+}
+
+impl Expression {
+    pub fn ty(&self) -> &Type {
+        match self {
+            Expression::Lit { ty, .. }
+            | Expression::Id { ty, .. }
+            | Expression::Call { ty, .. }
+            | Expression::Binary { ty, .. }
+            | Expression::Logical { ty, .. }
+            | Expression::Assign { ty, .. } => ty,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Lit { span, .. }
+            | Expression::Id { span, .. }
+            | Expression::Call { span, .. }
+            | Expression::Binary { span, .. }
+            | Expression::Logical { span, .. }
+            | Expression::Assign { span, .. } => *span,
+        }
+    }
+}
+
+fn infer_expression(infer: &mut Infer, env: &Env, expr: &syntax::Expression) -> Result<(Expression, Type, Subst), TypeError> {
+    match expr {
+        syntax::Expression::Lit(lit) => {
+            let (value, ty) = match lit {
+                syntax::Literal::Str(value, _) => (Literal::Str(value.clone()), Type::String),
+                syntax::Literal::Num(value, _) => (Literal::Num(*value), Type::Number),
+                syntax::Literal::Bool(value, _) => (Literal::Bool(*value), Type::Boolean),
+            };
+            Ok((
+                Expression::Lit {
+                    value,
+                    ty: ty.clone(),
+                    span: lit.span(),
+                },
+                ty,
+                Subst::new(),
+            ))
+        }
+        syntax::Expression::Id(id) => {
+            let scheme = env.get(&id.name).ok_or_else(|| TypeError::Unbound {
+                name: id.name.clone(),
+                span: id.span,
+            })?;
+            let ty = infer.instantiate(scheme);
+            Ok((
+                Expression::Id {
+                    name: id.name.clone(),
+                    ty: ty.clone(),
+                    span: id.span,
+                },
+                ty,
+                Subst::new(),
+            ))
+        }
+        syntax::Expression::Call(call) => {
+            let scheme = env.get(&call.function.name).ok_or_else(|| TypeError::Unbound {
+                name: call.function.name.clone(),
+                span: call.function.span,
+            })?;
+            let mut callee_ty = infer.instantiate(scheme);
+            let mut subst = Subst::new();
+            let mut arguments = vec![];
+            for argument in &call.arguments {
+                let (typed_argument, argument_ty, s) = infer_expression(infer, env, argument)?;
+                subst = compose(&s, &subst);
+                callee_ty = apply(&s, &callee_ty);
+
+                let result_ty = infer.fresh();
+                let applied = unify(
+                    &callee_ty,
+                    &Type::Fun(Box::new(argument_ty), Box::new(result_ty.clone())),
+                    call.span,
+                )?;
+                subst = compose(&applied, &subst);
+                callee_ty = apply(&applied, &result_ty);
+                arguments.push(typed_argument);
+            }
+            Ok((
+                Expression::Call {
+                    function: call.function.name.clone(),
+                    function_span: call.function.span,
+                    arguments,
+                    ty: callee_ty.clone(),
+                    span: call.span,
+                },
+                callee_ty,
+                subst,
+            ))
+        }
+        syntax::Expression::Binary(left, op, right, span) => {
+            let (typed_left, left_ty, s1) = infer_expression(infer, env, left)?;
+            let (typed_right, right_ty, s2) = infer_expression(infer, env, right)?;
+            let subst = compose(&s2, &s1);
+            // every binary operator the grammar produces today is numeric-only -- there's no
+            // string concatenation or operator overloading yet, so both sides must already be
+            // `Number` rather than `unify`-ing them against each other
+            let s3 = unify(&Type::Number, &apply(&subst, &left_ty), left.span())?;
+            let subst = compose(&s3, &subst);
+            let s4 = unify(&Type::Number, &apply(&subst, &right_ty), right.span())?;
+            let subst = compose(&s4, &subst);
+            let ty = match op {
+                syntax::BinaryOp::Add
+                | syntax::BinaryOp::Subtract
+                | syntax::BinaryOp::Multiply
+                | syntax::BinaryOp::Divide
+                | syntax::BinaryOp::Modulus => Type::Number,
+                syntax::BinaryOp::Equal
+                | syntax::BinaryOp::NotEqual
+                | syntax::BinaryOp::Less
+                | syntax::BinaryOp::LessEqual
+                | syntax::BinaryOp::Greater
+                | syntax::BinaryOp::GreaterEqual => Type::Boolean,
+            };
+            Ok((
+                Expression::Binary {
+                    op: *op,
+                    left: Box::new(typed_left),
+                    right: Box::new(typed_right),
+                    ty: ty.clone(),
+                    span: *span,
+                },
+                ty,
+                subst,
+            ))
+        }
+        syntax::Expression::Logical(left, op, right, span) => {
+            let (typed_left, left_ty, s1) = infer_expression(infer, env, left)?;
+            let (typed_right, right_ty, s2) = infer_expression(infer, env, right)?;
+            let subst = compose(&s2, &s1);
+            let s3 = unify(&Type::Boolean, &apply(&subst, &left_ty), left.span())?;
+            let subst = compose(&s3, &subst);
+            let s4 = unify(&Type::Boolean, &apply(&subst, &right_ty), right.span())?;
+            let subst = compose(&s4, &subst);
+            Ok((
+                Expression::Logical {
+                    op: *op,
+                    left: Box::new(typed_left),
+                    right: Box::new(typed_right),
+                    ty: Type::Boolean,
+                    span: *span,
+                },
+                Type::Boolean,
+                subst,
+            ))
+        }
+        syntax::Expression::Assign(name, op, value, span) => {
+            let scheme = env.get(&name.name).ok_or_else(|| TypeError::Unbound {
+                name: name.name.clone(),
+                span: name.span,
+            })?;
+            let var_ty = infer.instantiate(scheme);
+            let (typed_value, value_ty, mut subst) = infer_expression(infer, env, value)?;
+            let var_ty = apply(&subst, &var_ty);
+            let ty = match op {
+                syntax::AssignOp::Equal => {
+                    let s = unify(&var_ty, &value_ty, *span)?;
+                    subst = compose(&s, &subst);
+                    apply(&s, &var_ty)
+                }
+                // compound operators (`+=` and friends) piggyback on the same numeric-only rule
+                // `Binary` uses, rather than each assignment operator getting its own semantics
+                _ => {
+                    let s1 = unify(&Type::Number, &var_ty, *span)?;
+                    subst = compose(&s1, &subst);
+                    let s2 = unify(&Type::Number, &apply(&subst, &value_ty), *span)?;
+                    subst = compose(&s2, &subst);
+                    Type::Number
+                }
+            };
+            Ok((
+                Expression::Assign {
+                    name: name.name.clone(),
+                    op: *op,
+                    value: Box::new(typed_value),
+                    ty: ty.clone(),
+                    span: *span,
+                },
+                ty,
+                subst,
+            ))
+        }
+    }
+}
+
+/// Infers one statement against `env`, which `Let` and `FunctionDecl` extend in place so later
+/// statements in the same block see the new binding -- the `Scheme` recorded in `env` persists,
+/// but the statement's own accumulated `Subst` is also returned (rather than just being dropped,
+/// as before) so a caller that's checking a function body can apply whatever it discovers about
+/// the enclosing `ret_ty`/parameters (e.g. a `return 42;` pinning `ret_ty` to `Number`) before
+/// generalizing the function's type. `ret_ty` is the enclosing function's return type, if any, so
+/// a `return` inside it can unify against it; at the top level (and so for stray top-level
+/// `return`s) it's `None` and the value simply isn't checked against anything.
+fn infer_statement(
+    infer: &mut Infer,
+    env: &mut Env,
+    ret_ty: Option<&Type>,
+    stmt: &syntax::Statement,
+    errors: &mut Vec<TypeError>,
+) -> (Option<Statement>, Subst) {
+    match stmt {
+        syntax::Statement::Expr(expr) => match infer_expression(infer, env, expr) {
+            Ok((typed, _, subst)) => {
+                // an `Assign` unifies the binding's tracked type against the new value right
+                // there, but that unification is otherwise thrown away once this statement
+                // finishes -- write it back into `env` so the *next* statement's reference (or
+                // reassignment) sees the narrowed type instead of instantiating a fresh,
+                // disconnected copy of the original scheme
+                if let syntax::Expression::Assign(name, ..) = expr {
+                    if let Some(scheme) = env.get(&name.name) {
+                        let ty = apply(&subst, &scheme.ty);
+                        env.insert(name.name.clone(), Scheme { vars: vec![], ty });
+                    }
+                }
+                (Some(Statement::Expr(typed)), subst)
+            }
+            Err(error) => {
+                errors.push(error);
+                (None, Subst::new())
+            }
+        },
+        syntax::Statement::Let { name, init, span } => {
+            let (typed_init, ty, subst) = match init {
+                Some(init) => match infer_expression(infer, env, init) {
+                    Ok((typed_init, ty, subst)) => (Some(typed_init), ty, subst),
+                    Err(error) => {
+                        errors.push(error);
+                        return (None, Subst::new());
+                    }
+                },
+                None => (None, infer.fresh(), Subst::new()),
+            };
+            // kept monomorphic rather than `generalize`d: `let` bindings are mutable via
+            // `Expression::Assign`, and generalizing them would let each reference (and each
+            // reassignment) instantiate its own disconnected fresh copy, defeating the unification
+            // that's supposed to keep every use of the same binding at one type (no value
+            // restriction is implemented, so this is the simplest way not to unsoundly generalize
+            // a mutable binding)
+            env.insert(name.name.clone(), Scheme { vars: vec![], ty: ty.clone() });
+            (
+                Some(Statement::Let {
+                    name: name.name.clone(),
+                    ty,
+                    init: typed_init,
+                    span: *span,
+                }),
+                subst,
+            )
+        }
+        syntax::Statement::If {
+            test,
+            consequent,
+            alternate,
+            span,
+        } => match infer_expression(infer, env, test) {
+            Ok((typed_test, ty, mut subst)) => {
+                match unify(&Type::Boolean, &ty, test.span()) {
+                    Ok(s) => subst = compose(&s, &subst),
+                    Err(error) => errors.push(error),
+                }
+
+                let (consequent, consequent_subst) =
+                    infer_block(infer, &mut env.clone(), ret_ty, consequent, errors);
+                subst = compose(&consequent_subst, &subst);
+
+                let alternate = match alternate {
+                    Some(alternate) => {
+                        let (alternate, alternate_subst) =
+                            infer_block(infer, &mut env.clone(), ret_ty, alternate, errors);
+                        subst = compose(&alternate_subst, &subst);
+                        Some(alternate)
+                    }
+                    None => None,
+                };
+
+                (
+                    Some(Statement::If {
+                        test: typed_test,
+                        consequent,
+                        alternate,
+                        span: *span,
+                    }),
+                    subst,
+                )
+            }
+            Err(error) => {
+                errors.push(error);
+                (None, Subst::new())
+            }
+        },
+        syntax::Statement::While { test, body, span } => match infer_expression(infer, env, test) {
+            Ok((typed_test, ty, mut subst)) => {
+                match unify(&Type::Boolean, &ty, test.span()) {
+                    Ok(s) => subst = compose(&s, &subst),
+                    Err(error) => errors.push(error),
+                }
+
+                let (body, body_subst) = infer_block(infer, &mut env.clone(), ret_ty, body, errors);
+                subst = compose(&body_subst, &subst);
+
+                (
+                    Some(Statement::While {
+                        test: typed_test,
+                        body,
+                        span: *span,
+                    }),
+                    subst,
+                )
+            }
+            Err(error) => {
+                errors.push(error);
+                (None, Subst::new())
+            }
+        },
+        syntax::Statement::Return { value, span } => {
+            let (typed_value, subst) = match value {
+                Some(value) => match infer_expression(infer, env, value) {
+                    Ok((typed, ty, mut subst)) => {
+                        if let Some(ret_ty) = ret_ty {
+                            match unify(ret_ty, &ty, value.span()) {
+                                Ok(s) => subst = compose(&s, &subst),
+                                Err(error) => errors.push(error),
+                            }
+                        }
+                        (Some(typed), subst)
+                    }
+                    Err(error) => {
+                        errors.push(error);
+                        return (None, Subst::new());
+                    }
+                },
+                None => {
+                    let mut subst = Subst::new();
+                    if let Some(ret_ty) = ret_ty {
+                        match unify(ret_ty, &Type::Null, *span) {
+                            Ok(s) => subst = compose(&s, &subst),
+                            Err(error) => errors.push(error),
+                        }
+                    }
+                    (None, subst)
+                }
+            };
+            (
+                Some(Statement::Return {
+                    value: typed_value,
+                    span: *span,
+                }),
+                subst,
+            )
+        }
+        syntax::Statement::FunctionDecl {
+            name,
+            params,
+            body,
+            span,
+        } => {
+            let param_tys: Vec<Type> = params.iter().map(|_| infer.fresh()).collect();
+            let ret = infer.fresh();
+            let fn_ty = param_tys.iter().rev().fold(ret.clone(), |acc, param_ty| {
+                Type::Fun(Box::new(param_ty.clone()), Box::new(acc))
+            });
+
+            // bound monomorphically while checking the body, so a recursive call to `name` unifies
+            // against the exact type variables the rest of the body is using, not a fresh
+            // instantiation of them
+            let mut inner_env = env.clone();
+            inner_env.insert(
+                name.name.clone(),
+                Scheme {
+                    vars: vec![],
+                    ty: fn_ty.clone(),
+                },
+            );
+            for (param, ty) in params.iter().zip(&param_tys) {
+                inner_env.insert(
+                    param.name.clone(),
+                    Scheme {
+                        vars: vec![],
+                        ty: ty.clone(),
+                    },
+                );
+            }
+            let (body, subst) = infer_block(infer, &mut inner_env, Some(&ret), body, errors);
+
+            // `ret`/`param_tys` started out as unconstrained fresh variables; apply whatever the
+            // body's inference pinned them to (a `return`'s unification against `ret`, most
+            // commonly) before generalizing, or the function would get generalized over its
+            // original unconstrained variables instead of its actual inferred signature
+            let ret = apply(&subst, &ret);
+            let param_tys: Vec<Type> = param_tys.iter().map(|ty| apply(&subst, ty)).collect();
+            let fn_ty = apply(&subst, &fn_ty);
+
+            env.insert(name.name.clone(), generalize(env, &fn_ty));
+            (
+                Some(Statement::FunctionDecl {
+                    name: name.name.clone(),
+                    params: params
+                        .iter()
+                        .zip(param_tys)
+                        .map(|(param, ty)| (param.name.clone(), ty))
+                        .collect(),
+                    ret,
+                    body,
+                    span: *span,
+                }),
+                subst,
+            )
+        }
+    }
+}
+
+fn infer_block(
+    infer: &mut Infer,
+    env: &mut Env,
+    ret_ty: Option<&Type>,
+    body: &[syntax::Statement],
+    errors: &mut Vec<TypeError>,
+) -> (Vec<Statement>, Subst) {
+    let mut subst = Subst::new();
+    let mut statements = vec![];
+    for stmt in body {
+        let (typed, stmt_subst) = infer_statement(infer, env, ret_ty, stmt, errors);
+        subst = compose(&stmt_subst, &subst);
+        if let Some(typed) = typed {
+            statements.push(typed);
+        }
+    }
+    (statements, subst)
+}
+
+/// Walks `file` running Algorithm W over every top-level statement, seeding the environment with
+/// `builtin_env`. Like `semantics::check`, a type error in one statement doesn't stop inference on
+/// the others, but unlike `semantics::check`'s error list, the returned `File` only exists at all if
+/// every statement typed cleanly -- there's no well-typed IR to hand `compiler::compile_file` if
+/// even one statement doesn't have one.
+pub fn infer(file: &syntax::File) -> Result<File, Vec<TypeError>> {
+    let mut infer = Infer::new();
+    let mut env = builtin_env();
+    let mut errors = vec![];
+    let (body, _subst) = infer_block(&mut infer, &mut env, None, &file.body, &mut errors);
+
+    if errors.is_empty() {
+        Ok(File { body })
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span() -> Span {
+        Span {
+            start: tree_sitter::Point { row: 0, column: 0 },
+            end: tree_sitter::Point { row: 0, column: 1 },
+        }
+    }
+
+    fn id(name: &str) -> syntax::Identifier {
+        syntax::Identifier {
+            name: String::from(name),
+            span: span(),
+        }
+    }
+
+    fn lit(value: &str) -> syntax::Expression {
+        syntax::Expression::Lit(syntax::Literal::Str(String::from(value), span()))
+    }
+
+    fn file(statements: Vec<syntax::Statement>) -> syntax::File {
+        syntax::File { body: statements }
+    }
+
+    fn expr_ty(stmt: &Statement) -> &Type {
+        match stmt {
+            Statement::Expr(expr) => expr.ty(),
+            other => panic!("expected an expression statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_valid_call_types_cleanly() {
+        let f = file(vec![syntax::Statement::Expr(syntax::Expression::Call(
+            syntax::Call {
+                function: id("print"),
+                arguments: vec![lit("hello")],
+                span: span(),
+            },
+        ))]);
+        let typed = infer(&f).unwrap();
+        assert_eq!(typed.body.len(), 1);
+        assert_eq!(*expr_ty(&typed.body[0]), Type::Null);
+    }
+
+    #[test]
+    fn test_argument_type_mismatch_is_reported() {
+        // `print` takes a `String`, so passing it the `Null` that another `print(...)` call
+        // returns should fail to unify rather than silently compiling
+        let f = file(vec![syntax::Statement::Expr(syntax::Expression::Call(
+            syntax::Call {
+                function: id("print"),
+                arguments: vec![syntax::Expression::Call(syntax::Call {
+                    function: id("print"),
+                    arguments: vec![lit("hello")],
+                    span: span(),
+                })],
+                span: span(),
+            },
+        ))]);
+        assert_eq!(
+            infer(&f),
+            Err(vec![TypeError::Mismatch {
+                expected: String::from("String"),
+                found: String::from("Null"),
+                span: span(),
+            }]),
+        );
+    }
+
+    #[test]
+    fn test_unbound_name_is_reported() {
+        let f = file(vec![syntax::Statement::Expr(syntax::Expression::Id(id(
+            "nonexistent",
+        )))]);
+        assert_eq!(
+            infer(&f),
+            Err(vec![TypeError::Unbound {
+                name: String::from("nonexistent"),
+                span: span(),
+            }]),
+        );
+    }
+
+    #[test]
+    fn test_let_binding_is_visible_to_later_statements() {
+        let f = file(vec![
+            syntax::Statement::Let {
+                name: id("x"),
+                init: Some(syntax::Expression::Lit(syntax::Literal::Num(1.0, span()))),
+                span: span(),
+            },
+            syntax::Statement::Expr(syntax::Expression::Id(id("x"))),
+        ]);
+        let typed = infer(&f).unwrap();
+        assert_eq!(*expr_ty(&typed.body[1]), Type::Number);
+    }
+
+    #[test]
+    fn test_reassigning_a_let_binding_to_a_different_type_is_reported() {
+        // `let x; x = 1; x = "hi";` -- the second assignment must be checked against the type the
+        // first one settled on, not a fresh, disconnected type variable
+        let f = file(vec![
+            syntax::Statement::Let {
+                name: id("x"),
+                init: None,
+                span: span(),
+            },
+            syntax::Statement::Expr(syntax::Expression::Assign(
+                id("x"),
+                syntax::AssignOp::Equal,
+                Box::new(syntax::Expression::Lit(syntax::Literal::Num(1.0, span()))),
+                span(),
+            )),
+            syntax::Statement::Expr(syntax::Expression::Assign(
+                id("x"),
+                syntax::AssignOp::Equal,
+                Box::new(lit("hi")),
+                span(),
+            )),
+        ]);
+        assert_eq!(
+            infer(&f),
+            Err(vec![TypeError::Mismatch {
+                expected: String::from("Number"),
+                found: String::from("String"),
+                span: span(),
+            }]),
+        );
+    }
+
+    #[test]
+    fn test_uninitialized_let_binding_is_narrowed_by_its_first_use() {
+        // an uninitialized `let` starts out as a fresh type variable; once `x = 1` pins it down,
+        // a later read of `x` should see `Number`, not another unrelated fresh variable
+        let f = file(vec![
+            syntax::Statement::Let {
+                name: id("x"),
+                init: None,
+                span: span(),
+            },
+            syntax::Statement::Expr(syntax::Expression::Assign(
+                id("x"),
+                syntax::AssignOp::Equal,
+                Box::new(syntax::Expression::Lit(syntax::Literal::Num(1.0, span()))),
+                span(),
+            )),
+            syntax::Statement::Expr(syntax::Expression::Id(id("x"))),
+        ]);
+        let typed = infer(&f).unwrap();
+        assert_eq!(*expr_ty(&typed.body[2]), Type::Number);
+    }
+
+    #[test]
+    fn test_if_condition_must_be_boolean() {
+        let f = file(vec![syntax::Statement::If {
+            test: syntax::Expression::Lit(syntax::Literal::Num(1.0, span())),
+            consequent: vec![],
+            alternate: None,
+            span: span(),
+        }]);
+        assert_eq!(
+            infer(&f),
+            Err(vec![TypeError::Mismatch {
+                expected: String::from("Boolean"),
+                found: String::from("Number"),
+                span: span(),
+            }]),
+        );
+    }
+
+    #[test]
+    fn test_function_return_type_is_checked_against_call_site() {
+        let f = file(vec![
+            syntax::Statement::FunctionDecl {
+                name: id("answer"),
+                params: vec![],
+                body: vec![syntax::Statement::Return {
+                    value: Some(syntax::Expression::Lit(syntax::Literal::Num(42.0, span()))),
+                    span: span(),
+                }],
+                span: span(),
+            },
+            syntax::Statement::Expr(syntax::Expression::Call(syntax::Call {
+                function: id("answer"),
+                arguments: vec![],
+                span: span(),
+            })),
+        ]);
+        let typed = infer(&f).unwrap();
+        assert_eq!(*expr_ty(&typed.body[1]), Type::Number);
+    }
+}
@@ -0,0 +1,71 @@
+// discovers and watches `.qn` files that live on disk but haven't (yet, or ever) been opened in
+// the editor, so the rest of the database can treat a whole workspace as known instead of just
+// whatever documents happen to be open; see `db::QueryGroup::workspace_files`
+
+use notify::Watcher as _;
+use std::{path::Path, sync::mpsc};
+use url::Url;
+use walkdir::WalkDir;
+
+fn is_quench_file(path: &Path) -> bool {
+    path.extension().map_or(false, |ext| ext == "qn")
+}
+
+/// Walks `root` recursively and reads every `.qn` file found. Files that can't be turned into a
+/// `file://` URI or fail to read (e.g. a dangling symlink) are silently skipped, same as a
+/// directory listing would just omit them.
+pub fn scan(root: &Path) -> Vec<(Url, String)> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| is_quench_file(entry.path()))
+        .filter_map(|entry| {
+            let uri = Url::from_file_path(entry.path()).ok()?;
+            let text = std::fs::read_to_string(entry.path()).ok()?;
+            Some((uri, text))
+        })
+        .collect()
+}
+
+/// A change to a `.qn` file observed on disk, outside of the editor.
+#[derive(Debug)]
+pub enum Change {
+    Updated(Url, String),
+    Removed(Url),
+}
+
+/// Watches `root` recursively for changes to `.qn` files. The returned `Watcher` must be kept
+/// alive for as long as events are wanted; dropping it stops the watch. Events are delivered on
+/// the returned channel from a dedicated background thread.
+pub fn watch(root: &Path) -> notify::Result<(notify::RecommendedWatcher, mpsc::Receiver<Change>)> {
+    let (raw_tx, raw_rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(raw_tx)?;
+    watcher.watch(root, notify::RecursiveMode::Recursive)?;
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for event in raw_rx {
+            let Ok(event) = event else { continue };
+            for path in &event.paths {
+                if !is_quench_file(path) {
+                    continue;
+                }
+                let Ok(uri) = Url::from_file_path(path) else { continue };
+                let change = match event.kind {
+                    notify::EventKind::Remove(_) => Change::Removed(uri),
+                    notify::EventKind::Create(_) | notify::EventKind::Modify(_) => {
+                        match std::fs::read_to_string(path) {
+                            Ok(text) => Change::Updated(uri, text),
+                            Err(_) => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                // the other end hangs up when the server shuts down; nothing to do about that here
+                let _ = tx.send(change);
+            }
+        }
+    });
+
+    Ok((watcher, rx))
+}
@@ -0,0 +1,157 @@
+// shared by `tests/cli.rs` and `tests/doctest.rs`: both end up compiling a Quench source file and
+// diffing its compile/run output against an `Example` recorded in a goldenfile, one from the
+// `examples/` directory and the other from fenced code blocks in Markdown docs.
+
+use assert_cmd::{assert::OutputAssertExt, prelude::CommandCargoExt};
+use goldenfile::Mint;
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    str,
+};
+
+pub const GOLDENFILES: &str = "tests/goldenfiles";
+
+pub fn subcmd<I, S>(name: &str, args: I) -> Output
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    Command::cargo_bin("quench")
+        .unwrap()
+        .env("NO_COLOR", "1")
+        .arg(name)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+pub fn to_nonempty_string(bytes: Vec<u8>) -> Option<String> {
+    if bytes.is_empty() {
+        None
+    } else {
+        Some(str::from_utf8(&bytes).unwrap().to_string())
+    }
+}
+
+#[derive(Debug, serde::Deserialize, PartialEq)]
+pub struct Example {
+    pub args: Option<Vec<String>>,
+    pub compile: Option<String>,
+    pub status: Option<i32>,
+    pub out: Option<String>,
+    pub err: Option<String>,
+}
+
+pub fn try_example(stem: String, path: PathBuf, args: Option<Vec<String>>) -> (String, Example) {
+    let example = {
+        let Output {
+            status,
+            stdout,
+            stderr,
+        } = subcmd("compile", &[&path]);
+        if status.success() {
+            assert!(stderr.is_empty());
+            {
+                let mut mint = Mint::new("examples");
+                let mut file = mint
+                    .new_goldenfile(Path::new(&stem).with_extension("js"))
+                    .unwrap();
+                file.write_all(&stdout).unwrap();
+            }
+
+            let Output {
+                status,
+                stdout,
+                stderr,
+            } = subcmd("run", {
+                let mut full_args = vec![path.to_str().unwrap().to_string()];
+                full_args.extend_from_slice(args.as_ref().unwrap_or(&vec![]));
+                full_args
+            });
+            Example {
+                args,
+                compile: None,
+                // we don't just use status.code() here, because we assume there was an exit code
+                status: {
+                    let code = status.code().unwrap();
+                    if code == 0 {
+                        None
+                    } else {
+                        Some(code)
+                    }
+                },
+                out: to_nonempty_string(stdout),
+                err: to_nonempty_string(stderr),
+            }
+        } else {
+            assert!(stdout.is_empty());
+            Example {
+                args,
+                compile: to_nonempty_string(stderr),
+                status: None,
+                out: None,
+                err: None,
+            }
+        }
+    };
+
+    (stem, example)
+}
+
+fn write_literal(writer: &mut impl Write, key: &str, value: &Option<String>) -> io::Result<()> {
+    if let Some(string) = value {
+        write!(writer, "  {}: |\n", key)?;
+        for line in string.lines() {
+            write!(writer, "    {}\n", line)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn write_example(writer: &mut impl Write, name: &str, example: &Example) -> io::Result<()> {
+    write!(writer, "{}:\n", name)?;
+    let Example {
+        args,
+        compile,
+        status,
+        out,
+        err,
+    } = example;
+    if let Some(args) = args {
+        write!(writer, "  args:\n")?;
+        for arg in args {
+            // this will probably eventually have to be made more robust
+            write!(writer, "    - {}\n", arg)?;
+        }
+    }
+    write_literal(writer, "compile", compile)?;
+    if let Some(code) = status {
+        write!(writer, "  status: {}\n", code)?;
+    }
+    write_literal(writer, "out", out)?;
+    write_literal(writer, "err", err)?;
+    Ok(())
+}
+
+pub type Examples = BTreeMap<String, Example>;
+
+pub fn read_examples(name: &str) -> Examples {
+    serde_yaml::from_reader(File::open(Path::new(GOLDENFILES).join(name)).unwrap()).unwrap()
+}
+
+pub fn write_examples(writer: &mut impl Write, examples: &Examples) -> io::Result<()> {
+    let mut it = examples.iter();
+    if let Some((name, example)) = it.next() {
+        write_example(writer, name, example)?;
+        for (name, example) in it {
+            write!(writer, "\n")?;
+            write_example(writer, name, example)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+// a Markdown doctest generator: globs `**/*.md`, extracts every ```quench fenced code block as a
+// synthetic source file, and feeds it through the same compile-run-capture pipeline as
+// `tests/cli.rs`'s `examples/` directory, so documentation examples can't silently rot. A fence
+// tagged `quench,no_run` or `quench,ignore` (mirroring rustdoc's own fence attributes) is skipped.
+
+mod common;
+
+use common::{read_examples, try_example, write_examples, Examples, GOLDENFILES};
+use pretty_assertions::assert_eq;
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag};
+use std::{ffi::OsStr, fs, io::Write, path::PathBuf};
+use walkdir::WalkDir;
+
+const DOCTESTS: &str = "doctests.yml";
+
+/// Splits a fence's info string (the text right after the opening backticks, e.g.
+/// `quench,no_run`) into its language token and comma-separated attributes.
+fn parse_info(info: &str) -> (&str, Vec<&str>) {
+    let mut parts = info.split(',').map(str::trim);
+    (parts.next().unwrap_or(""), parts.collect())
+}
+
+/// Extracts every ```quench fenced block from `markdown`, in document order, paired with its
+/// fence attributes.
+fn extract_fences(markdown: &str) -> Vec<(Vec<String>, String)> {
+    let mut fences = vec![];
+    let mut current: Option<(Vec<String>, String)> = None;
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                let (lang, attrs) = parse_info(&info);
+                if lang == "quench" {
+                    current = Some((attrs.into_iter().map(String::from).collect(), String::new()));
+                }
+            }
+            Event::Text(text) => {
+                if let Some((_, code)) = &mut current {
+                    code.push_str(&text);
+                }
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                if let Some(fence) = current.take() {
+                    fences.push(fence);
+                }
+            }
+            _ => {}
+        }
+    }
+    fences
+}
+
+fn write_fence(code: &str) -> PathBuf {
+    let mut file = tempfile::Builder::new().suffix(".qn").tempfile().unwrap();
+    file.write_all(code.as_bytes()).unwrap();
+    file.into_temp_path().keep().unwrap()
+}
+
+fn is_markdown_file(path: &std::path::Path) -> bool {
+    path.extension() == Some(OsStr::new("md"))
+        && !path.components().any(|c| c.as_os_str() == "target")
+}
+
+#[test]
+fn test_doctests() {
+    let actual = read_examples(DOCTESTS);
+
+    let mut expected = Examples::new();
+    for entry in WalkDir::new(".").into_iter().filter_map(Result::ok) {
+        let path = entry.path();
+        if !is_markdown_file(path) {
+            continue;
+        }
+
+        let markdown = fs::read_to_string(path).unwrap();
+        for (index, (attrs, code)) in extract_fences(&markdown).into_iter().enumerate() {
+            if attrs.iter().any(|attr| attr == "no_run" || attr == "ignore") {
+                continue;
+            }
+
+            let name = format!("{}#{}", path.display(), index + 1);
+            let args = actual.get(&name).and_then(|example| example.args.clone());
+            let (name, example) = try_example(name, write_fence(&code), args);
+            expected.insert(name, example);
+        }
+    }
+
+    write_examples(
+        &mut goldenfile::Mint::new(GOLDENFILES)
+            .new_goldenfile(DOCTESTS)
+            .unwrap(),
+        &expected,
+    )
+    .unwrap();
+
+    assert_eq!(read_examples(DOCTESTS), expected);
+}